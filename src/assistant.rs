@@ -0,0 +1,366 @@
+//! # Commands from requests in plain language, proposed by a model that Ollama runs.
+//!
+//! An input like `? find large files here` isn't run. The request is sent to the model in
+//! the `ollama-model` setting at `ollama-url`, with the working directory, the OS and the
+//! recent inputs, and the proposed command streams into the input instead. It only runs
+//! once it is accepted, like any other input.
+//!
+//! The `explain` builtin sends the last failed input, its exit status and the end of its
+//! stderr, if the `capture-stderr` setting is on, to the model, and streams an explanation
+//! with a fix.
+
+use crate::runtime::{lookup, set_variable, Scope, Value};
+use ollama_rs::generation::completion::request::GenerationRequest;
+use ollama_rs::Ollama;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use tokio_stream::StreamExt;
+
+/// The prefix of inputs that are requests for a command.
+pub const REQUEST_PREFIX: char = '?';
+
+/// How many of the recent inputs are sent with a request, as examples of the syntax.
+const HISTORY_CONTEXT: usize = 10;
+
+/// The longest answer that is taken as a command, in bytes.
+const MAX_COMMAND_LENGTH: usize = 4096;
+
+/// How many lines of the end of stderr are sent to explain a failure.
+const STDERR_CONTEXT: usize = 20;
+
+const COMMAND_INSTRUCTIONS: &str = "\
+You write commands for shesh, a shell. It runs programs like other shells, and has builtins \
+that pass tables through pipelines, like `ls | where size > 1MiB | sort-by modified`. The \
+builtins are ls, where, sort-by, select, first, last, length, group-by, uniq, lines, open, \
+from and to. Answer the request with a single command that does it, without explanations, \
+quotes or code fences.";
+
+const EXPLAIN_INSTRUCTIONS: &str = "\
+You explain why commands in shesh, a shell, failed. It runs programs like other shells, and \
+has builtins that pass tables through pipelines, like `ls | where size > 1MiB`. Explain the \
+failure in one or two sentences, then suggest a fix, as briefly as possible.";
+
+/// The Ollama server and the model to use, from the settings.
+pub struct Model {
+    pub url: String,
+    pub name: String,
+}
+
+impl Model {
+    pub fn from_scope(scope: &Scope) -> Self {
+        let setting = |name| lookup(scope, name).and_then(Value::get_string).unwrap_or_default().to_string();
+
+        Self {
+            url: setting("ollama-url"),
+            name: setting("ollama-model"),
+        }
+    }
+}
+
+/// Streams the answer of the model to the prompt in chunks, from a background thread.
+/// The channel closes after the answer, or after an error, which is sent as `Err`.
+/// Dropping the receiver stops the answer.
+pub fn generate(model: &Model, instructions: &str, prompt: String) -> Receiver<Result<String, String>> {
+    let (sender, receiver) = channel();
+    let url = model.url.clone();
+    let request = GenerationRequest::new(model.name.clone(), prompt).system(instructions.to_string());
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the async runtime");
+
+        let result = runtime.block_on(async {
+            let ollama = Ollama::try_new(url.as_str())
+                .map_err(|error| format!("Invalid Ollama URL `{}`: {}", url, error))?;
+
+            let mut stream = ollama.generate_stream(request).await
+                .map_err(|error| format!("Failed to reach Ollama at {}: {}", url, message(error)))?;
+
+            while let Some(chunk) = stream.next().await {
+                for response in chunk.map_err(message)? {
+                    if sender.send(Ok(response.response)).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            let _ = sender.send(Err(error));
+        }
+    });
+
+    receiver
+}
+
+/// Returns the message of the error, without the prefix that ollama-rs adds.
+fn message(error: ollama_rs::error::OllamaError) -> String {
+    let message = error.to_string();
+
+    match message.strip_prefix("An error occurred with ollama-rs: ") {
+        Some(message) => message.to_string(),
+        None => message,
+    }
+}
+
+pub enum Progress {
+    Streaming,
+    Done,
+    Failed(String),
+}
+
+/// A command that the model proposes for a request, while it streams in.
+pub struct Proposal {
+    receiver: Receiver<Result<String, String>>,
+    answer: String,
+    /// The input with the request, to restore it if the request fails.
+    pub input: String,
+}
+
+impl Proposal {
+    /// Sends the request, with the working directory and the recent inputs, the newest last.
+    pub fn start(model: &Model, input: &str, request: &str, directory: &str, history: &[&str]) -> Self {
+        let recent = &history[history.len().saturating_sub(HISTORY_CONTEXT)..];
+
+        let prompt = format!(
+            "Working directory: {}\nOS: {}\nRecent inputs:\n{}\n\nRequest: {}",
+            directory,
+            std::env::consts::OS,
+            recent.join("\n"),
+            request
+        );
+
+        Self {
+            receiver: generate(model, COMMAND_INSTRUCTIONS, prompt),
+            answer: String::new(),
+            input: input.to_string(),
+        }
+    }
+
+    /// Takes the chunks of the answer that arrived, without waiting for more. Answers
+    /// without a command and answers too long to be one fail.
+    pub fn poll(&mut self) -> Progress {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Ok(chunk)) => self.answer.push_str(&chunk),
+                Ok(Err(error)) => return Progress::Failed(error),
+                Err(TryRecvError::Empty) => return Progress::Streaming,
+                Err(TryRecvError::Disconnected) if self.command().is_empty() => {
+                    return Progress::Failed("The model proposed no command".to_string());
+                }
+                Err(TryRecvError::Disconnected) => return Progress::Done,
+            }
+
+            if self.answer.len() > MAX_COMMAND_LENGTH {
+                return Progress::Failed("The proposed command is too long".to_string());
+            }
+        }
+    }
+
+    /// Returns the command as far as it arrived.
+    #[inline]
+    pub fn command(&self) -> String {
+        clean_command(&self.answer)
+    }
+}
+
+/// An input that failed, which the `last-failure` variable keeps for `explain`.
+#[derive(PartialEq, Debug)]
+pub struct Failure {
+    pub input: String,
+    pub status: i64,
+    /// The error of the shell, or else the end of the stderr of the programs if it was
+    /// captured.
+    pub errors: String,
+}
+
+impl Failure {
+    pub fn remember(self, scope: &mut Scope) {
+        set_variable(scope, "last-failure", Value::Record(vec![
+            ("input".to_string(), Value::String(self.input)),
+            ("status".to_string(), Value::Int(self.status)),
+            ("errors".to_string(), Value::String(self.errors)),
+        ]));
+    }
+
+    pub fn last(scope: &Scope) -> Option<Self> {
+        let Some(Value::Record(fields)) = lookup(scope, "last-failure") else {
+            return None;
+        };
+
+        let field = |name| fields.iter().find(|(field, _)| field == name).map(|(_, value)| value);
+
+        match (field("input"), field("status"), field("errors")) {
+            (Some(Value::String(input)), Some(Value::Int(status)), Some(Value::String(errors))) => Some(Self {
+                input: input.clone(),
+                status: *status,
+                errors: errors.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Streams an explanation of the failure with a fix, like [generate].
+    pub fn explain(&self, model: &Model) -> Receiver<Result<String, String>> {
+        let lines = self.errors.lines().collect::<Vec<_>>();
+        let tail = &lines[lines.len().saturating_sub(STDERR_CONTEXT)..];
+
+        let prompt = format!(
+            "OS: {}\nCommand: {}\nExit status: {}\nErrors:\n{}",
+            std::env::consts::OS,
+            self.input,
+            self.status,
+            tail.join("\n")
+        );
+
+        generate(model, EXPLAIN_INSTRUCTIONS, prompt)
+    }
+}
+
+/// Removes code fences and backticks around the command, which models add anyway.
+fn clean_command(answer: &str) -> String {
+    let lines = answer
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>();
+
+    lines.join("\n").trim().trim_matches('`').trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Answers one request like Ollama does, with a line of JSON per chunk. Returns the
+    /// URL of the server and the body of the request.
+    fn mock_server(chunks: &[&str]) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let chunks = chunks.iter().map(|chunk| chunk.to_string()).collect::<Vec<_>>();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n").unwrap();
+
+            for (i, chunk) in chunks.iter().enumerate() {
+                let line = format!(
+                    "{{\"model\":\"test\",\"created_at\":\"2024-11-19T20:30:00Z\",\"response\":{:?},\"done\":{}}}\n",
+                    chunk,
+                    i + 1 == chunks.len()
+                );
+
+                stream.write_all(line.as_bytes()).unwrap();
+                stream.flush().unwrap();
+            }
+
+            String::from_utf8(body).unwrap()
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    pub fn test_proposal() {
+        let (url, server) = mock_server(&["```sh\n", "ls | where", " size > 1MiB", "\n```"]);
+        let model = Model { url, name: "test".to_string() };
+
+        let mut proposal = Proposal::start(&model, "? find large files", "find large files", "/home/me", &["ls", "cd src"]);
+
+        while let Progress::Streaming = proposal.poll() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(proposal.command(), "ls | where size > 1MiB");
+
+        let body = server.join().unwrap();
+        assert!(body.contains("\"model\":\"test\""));
+        assert!(body.contains("Request: find large files"));
+        assert!(body.contains("Working directory: /home/me"));
+        assert!(body.contains("cd src"));
+
+        assert_eq!(clean_command("`ls -la`"), "ls -la");
+        assert_eq!(clean_command("```"), "");
+
+        let long = "x".repeat(MAX_COMMAND_LENGTH + 1);
+
+        for (chunks, expected) in [
+            (&["```sh\n", "```"][..], "The model proposed no command"),
+            (&[long.as_str()][..], "The proposed command is too long"),
+        ] {
+            let (url, server) = mock_server(chunks);
+            let model = Model { url, name: "test".to_string() };
+            let mut proposal = Proposal::start(&model, "? list files", "list files", "/home/me", &[]);
+
+            let progress = loop {
+                match proposal.poll() {
+                    Progress::Streaming => std::thread::sleep(std::time::Duration::from_millis(10)),
+                    progress => break progress,
+                }
+            };
+
+            assert!(matches!(progress, Progress::Failed(error) if error == expected));
+            server.join().unwrap();
+        }
+    }
+
+    #[test]
+    pub fn test_explain() {
+        let mut scope = Scope::new();
+        assert_eq!(Failure::last(&scope), None);
+
+        Failure {
+            input: "cargo biuld".to_string(),
+            status: 101,
+            errors: "error: no such command: `biuld`\n\n\tDid you mean `build`?".to_string(),
+        }.remember(&mut scope);
+
+        let failure = Failure::last(&scope).unwrap();
+        assert_eq!(failure.status, 101);
+
+        let (url, server) = mock_server(&["It is a typo.", " Run `cargo build`."]);
+        let model = Model { url, name: "test".to_string() };
+
+        let explanation = failure.explain(&model).into_iter().collect::<Result<String, String>>();
+        assert_eq!(explanation.as_deref(), Ok("It is a typo. Run `cargo build`."));
+
+        let body = server.join().unwrap();
+        assert!(body.contains("Command: cargo biuld"));
+        assert!(body.contains("Exit status: 101"));
+        assert!(body.contains("Did you mean `build`?"));
+
+        // Nothing listens on the port of a closed listener.
+        let url = format!("http://{}", TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap());
+        let model = Model { url: url.clone(), name: "test".to_string() };
+
+        let error = failure.explain(&model).into_iter().collect::<Result<String, String>>().unwrap_err();
+        assert!(error.starts_with(&format!("Failed to reach Ollama at {}", url)));
+    }
+}
\ No newline at end of file
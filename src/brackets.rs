@@ -0,0 +1,170 @@
+//! # Matching brackets and quotes, and typing them in pairs.
+//!
+//! The input is split into tokens by the [Lexer], so brackets inside strings are text.
+
+use crate::read::bytes::Cursor;
+use crate::read::error::ReadError;
+use crate::read::lex::Lexer;
+use crate::read::tokens::Token;
+use crate::text_box::TextBox;
+use std::ops::Range;
+
+/// The brackets and quotes of an input, as byte offsets.
+pub struct Brackets {
+    /// The offsets of matching opening and closing brackets or quotes.
+    pairs: Vec<(usize, usize)>,
+    /// The strings with their quotes, and whether they are terminated.
+    strings: Vec<(Range<usize>, bool)>,
+}
+
+impl Brackets {
+    pub fn new(input: &str) -> Self {
+        let mut lexer = Lexer::new(Cursor::new(input));
+        let mut pairs = Vec::new();
+        let mut strings = Vec::new();
+        let mut open = Vec::<(usize, Token)>::new();
+
+        loop {
+            let token = match lexer.next() {
+                Ok(token) => token,
+                Err(error) => {
+                    let range = error.range.start as usize..error.range.end as usize;
+
+                    match error.value {
+                        ReadError::UnterminatedString => strings.push((range, false)),
+                        // Skip what can't be lexed, as long as the lexer moves on.
+                        _ if !range.is_empty() => continue,
+                        _ => {}
+                    }
+
+                    break;
+                }
+            };
+
+            let start = token.range.start as usize;
+
+            let closing = match token.value {
+                Token::EndOfInput => break,
+                Token::LeftParenthesis | Token::LeftBracket | Token::LeftBrace => {
+                    open.push((start, token.value));
+                    continue;
+                }
+                Token::RightParenthesis => Token::LeftParenthesis,
+                Token::RightBracket => Token::LeftBracket,
+                Token::RightBrace => Token::LeftBrace,
+                Token::String(_) => {
+                    let end = token.range.end as usize;
+
+                    pairs.push((start, end - 1));
+                    strings.push((start..end, true));
+                    continue;
+                }
+                _ => continue,
+            };
+
+            // A closing bracket matches the innermost open one of its kind.
+            if let Some(index) = open.iter().rposition(|(_, opening)| *opening == closing) {
+                pairs.push((open[index].0, start));
+                open.truncate(index);
+            }
+        }
+
+        Self { pairs, strings }
+    }
+
+    /// Returns the offset of the bracket or quote that matches the one at `index`.
+    pub fn partner(&self, index: usize) -> Option<usize> {
+        self.pairs.iter().find_map(|&(open, close)| match index {
+            _ if index == open => Some(close),
+            _ if index == close => Some(open),
+            _ => None,
+        })
+    }
+
+    /// Returns `true` if a cursor at `index` is between the quotes of a string.
+    pub fn in_string(&self, index: usize) -> bool {
+        self.strings.iter().any(|(range, terminated)| {
+            range.start < index && (index < range.end || !terminated && index == range.end)
+        })
+    }
+}
+
+/// Returns the offsets of the bracket or quote at the cursor, or else left from it, and
+/// of its partner.
+pub fn matching(text_box: &TextBox) -> Option<(usize, usize)> {
+    let (left, right) = text_box.parts();
+    let brackets = Brackets::new(&format!("{}{}", left, right));
+    let cursor = left.len();
+
+    [Some(cursor), cursor.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .find_map(|index| Some((index, brackets.partner(index)?)))
+}
+
+/// Types the character, and with auto-pairing its closing bracket or quote too. Typing
+/// a closing bracket or quote that is right from the cursor moves over it instead.
+pub fn insert_paired(text_box: &mut TextBox, c: char) {
+    let (left, right) = text_box.parts();
+    let brackets = Brackets::new(&format!("{}{}", left, right));
+    let cursor = left.len();
+
+    let previous = left.chars().next_back();
+    let next = right.chars().next();
+
+    let closing = match c {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        // Apostrophes in words, like `it's`, are not quotes.
+        '"' | '\'' if !previous.is_some_and(char::is_alphanumeric)
+            && next.is_none_or(|next| next.is_whitespace() || matches!(next, ')' | ']' | '}')) => Some(c),
+        _ => None,
+    };
+
+    let is_closing = matches!(c, ')' | ']' | '}' | '"' | '\'');
+
+    if next == Some(c) && is_closing && brackets.partner(cursor).is_some_and(|partner| partner < cursor) {
+        text_box.move_cursor_right();
+    } else if let Some(closing) = closing.filter(|_| !brackets.in_string(cursor)) {
+        text_box.insert_char(c);
+        text_box.insert_char(closing);
+        text_box.move_cursor_left();
+    } else {
+        text_box.insert_char(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_paired(input: &str) -> (String, String) {
+        let mut text_box = TextBox::new();
+
+        for c in input.chars() {
+            insert_paired(&mut text_box, c);
+        }
+
+        let (left, right) = text_box.parts();
+        (left.to_string(), right.to_string())
+    }
+
+    #[test]
+    pub fn test_brackets() {
+        let brackets = Brackets::new("(1 + [2]) | echo \"(\" {");
+        assert_eq!(brackets.partner(0), Some(8));
+        assert_eq!(brackets.partner(7), Some(5));
+        assert_eq!(brackets.partner(17), Some(19));
+        assert_eq!(brackets.partner(18), None);
+        assert_eq!(brackets.partner(21), None);
+        assert!(brackets.in_string(18));
+        assert!(!brackets.in_string(20));
+        assert!(Brackets::new("echo 'ab").in_string(8));
+
+        assert_eq!(type_paired("(1 + [2"), ("(1 + [2".to_string(), "])".to_string()));
+        assert_eq!(type_paired("(1 + [2])"), ("(1 + [2])".to_string(), "".to_string()));
+        assert_eq!(type_paired("echo \"(a"), ("echo \"(a".to_string(), "\"".to_string()));
+        assert_eq!(type_paired("echo \"a\" it's"), ("echo \"a\" it's".to_string(), "".to_string()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+//! # Copying to the system clipboard through the terminal.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::Command;
+use std::fmt;
+
+/// Sets the system clipboard with the OSC 52 escape sequence. The terminal handles it,
+/// so copying works over SSH too.
+pub struct CopyToClipboard<'a>(pub &'a str);
+
+impl Command for CopyToClipboard<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b]52;c;{}\x07", STANDARD.encode(self.0))
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "OSC 52 needs ANSI support"))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,88 @@
+//! # Completing file names at the cursor.
+//!
+//! The word before the cursor is a path whose last part is completed with the entries of
+//! its directory, as far as they agree. Hidden entries are only completed after a `.`.
+
+use std::path::Path;
+
+/// Returns the text to insert after `left`, the input before the cursor, to complete the
+/// file name that ends it. A single directory is completed with a `/`.
+pub fn complete(left: &str, directory: &Path) -> Option<String> {
+    let word = left.rsplit(is_separator).next().unwrap_or(left);
+    let (parent, prefix) = word.rsplit_once('/').unwrap_or(("", word));
+
+    let search = match (parent, word.contains('/')) {
+        ("", true) => Path::new("/").to_path_buf(),
+        (parent, _) => directory.join(parent),
+    };
+
+    let mut candidates = std::fs::read_dir(search)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let visible = !name.starts_with('.') || prefix.starts_with('.');
+
+            (visible && name.starts_with(prefix)).then(|| (name, entry.path().is_dir()))
+        });
+
+    let (first, is_dir) = candidates.next()?;
+    let mut common = first.len();
+    let mut unique = true;
+
+    for (name, _) in candidates {
+        common = common_prefix(&first[..common], &name);
+        unique = false;
+    }
+
+    let mut completion = first[prefix.len()..common].to_string();
+
+    if unique && is_dir {
+        completion.push('/');
+    }
+
+    Some(completion).filter(|completion| !completion.is_empty())
+}
+
+/// Whether the char ends the word before it.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '|' | '(' | '[' | '{' | '\'' | '"')
+}
+
+/// Returns the length in bytes of the common start of the strings, at a char boundary.
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(a.len().min(b.len()), |((index, _), _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_complete() {
+        let directory = std::env::temp_dir().join(format!("shesh-test-complete-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        std::fs::create_dir_all(directory.join("src/read")).unwrap();
+        std::fs::write(directory.join("notes.txt"), "").unwrap();
+        std::fs::write(directory.join("notes.md"), "").unwrap();
+        std::fs::write(directory.join(".hidden"), "").unwrap();
+        std::fs::write(directory.join("src/résumé.txt"), "").unwrap();
+
+        assert_eq!(complete("ls s", &directory), Some("rc/".to_string()));
+        assert_eq!(complete("ls no", &directory), Some("tes.".to_string()));
+        assert_eq!(complete("ls notes.", &directory), None);
+        assert_eq!(complete("ls src/r", &directory), None);
+        assert_eq!(complete("ls src/ré", &directory), Some("sumé.txt".to_string()));
+        assert_eq!(complete("ls src/re", &directory), Some("ad/".to_string()));
+        assert_eq!(complete("open 'n", &directory), Some("otes.".to_string()));
+        assert_eq!(complete("ls .h", &directory), Some("idden".to_string()));
+        assert_eq!(complete("ls x", &directory), None);
+        assert_eq!(complete("ls missing/", &directory), None);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,131 @@
+//! # The config file, `shesh/config.toml` in the user's config directory.
+//!
+//! Key bindings go into the `keys` table, from keys to action names, and the initial
+//! values of settings into the `settings` table:
+//!
+//! ```toml
+//! [keys]
+//! "alt-backspace" = "kill-word-left"
+//! "ctrl-z" = "redo"
+//!
+//! [settings]
+//! edit-mode = "vi"
+//! ollama-model = "qwen2.5-coder"
+//! ```
+
+use crate::keymap::{Action, Keymap};
+use crate::runtime::formats::toml;
+use crate::runtime::{RuntimeError, Value};
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    /// The settings to change at startup, like with `set`.
+    pub settings: Vec<(String, String)>,
+}
+
+impl Config {
+    /// Loads the config file, or the defaults if there is none. Invalid parts of the
+    /// file are skipped and returned as warnings, so that the shell starts anyway.
+    pub fn load() -> (Self, Vec<String>) {
+        let Some(path) = path() else {
+            return (Self::default(), Vec::new());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(error) => (Self::default(), vec![format!("{}: {}", path.display(), error)]),
+        }
+    }
+
+    pub fn parse(text: &str) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut warnings = Vec::new();
+
+        let fields = match toml::parse(text) {
+            Ok(Value::Record(fields)) => fields,
+            Ok(_) => Vec::new(),
+            Err(error) => {
+                let warning = match error.into_runtime_error(text) {
+                    RuntimeError::ParseError { line, column, message } => {
+                        format!("Failed to parse the config file at line {}, column {}: {}", line, column, message)
+                    }
+                    error => format!("Failed to parse the config file: {}", error),
+                };
+
+                return (config, vec![warning]);
+            }
+        };
+
+        for (name, value) in fields {
+            match (name.as_str(), value) {
+                ("keys", Value::Record(bindings)) => {
+                    for (key, action) in bindings {
+                        let Some(action) = action.get_string().and_then(Action::from_name) else {
+                            warnings.push(format!("Unknown action for `{}`: {}", key, action.to_argument()));
+                            continue;
+                        };
+
+                        if !config.keymap.bind(&key, action) {
+                            warnings.push(format!("Invalid key: `{}`", key));
+                        }
+                    }
+                }
+                ("settings", Value::Record(settings)) => {
+                    for (name, value) in settings {
+                        match value {
+                            Value::String(value) => config.settings.push((name, value)),
+                            value => warnings.push(format!("Invalid value for `{}`: {}", name, value.to_argument())),
+                        }
+                    }
+                }
+                (name, _) => warnings.push(format!("Unknown config entry: `{}`", name)),
+            }
+        }
+
+        (config, warnings)
+    }
+}
+
+/// Returns the path of the config file, in `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%`.
+pub fn path() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(directory.join("shesh").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    pub fn test_parse_config() {
+        let (config, warnings) = Config::parse(r#"
+            [keys]
+            "alt-backspace" = "kill-word-left"
+            "ctrl-z" = "redo"
+            "ctrl-q" = "explode"
+            "hyper-x" = "undo"
+
+            [settings]
+            edit-mode = "vi"
+            auto-pairs = true
+        "#);
+
+        assert_eq!(config.keymap.action(KeyCode::Backspace, KeyModifiers::ALT), Some(Action::KillWordLeft));
+        assert_eq!(config.keymap.action(KeyCode::Char('z'), KeyModifiers::CONTROL), Some(Action::Redo));
+        assert_eq!(config.keymap.action(KeyCode::Char('y'), KeyModifiers::CONTROL), Some(Action::Yank));
+        assert_eq!(config.settings, vec![("edit-mode".to_string(), "vi".to_string())]);
+        assert_eq!(warnings, vec![
+            "Unknown action for `ctrl-q`: explode",
+            "Invalid key: `hyper-x`",
+            "Invalid value for `auto-pairs`: true",
+        ]);
+    }
+}
\ No newline at end of file
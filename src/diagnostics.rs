@@ -0,0 +1,209 @@
+//! # Error messages that point into the input.
+
+use crate::read::bytes::Index;
+use crossterm::style::{Attribute, Color, SetAttribute, SetForegroundColor};
+use std::fmt::Write;
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
+
+/// A part of the input that a diagnostic points at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub range: Range<Index>,
+    pub message: String,
+    /// Primary labels mark the cause of the error, secondary labels related parts.
+    pub primary: bool,
+}
+
+/// An error with a message, labels in the input and notes or hints below it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub hints: Vec<String>,
+}
+
+impl Diagnostic {
+    #[inline]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            hints: Vec::new(),
+        }
+    }
+
+    pub fn with_primary(mut self, range: Range<Index>, message: impl Into<String>) -> Self {
+        self.labels.push(Label { range, message: message.into(), primary: true });
+        self
+    }
+
+    pub fn with_secondary(mut self, range: Range<Index>, message: impl Into<String>) -> Self {
+        self.labels.push(Label { range, message: message.into(), primary: false });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hints.push(hint.into());
+        self
+    }
+
+    /// Renders the diagnostic for the given input. Every line of the input with a label
+    /// is written with the labeled parts underlined below it, `^` for primary and `-`
+    /// for secondary labels. Line numbers are only written for inputs of several lines.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "{}{}error{}: {}{}",
+            SetAttribute(Attribute::Bold),
+            SetForegroundColor(Color::Red),
+            SetForegroundColor(Color::Reset),
+            self.message,
+            SetAttribute(Attribute::Reset)
+        );
+
+        let multiline = source.contains('\n');
+        let mut line_start = 0;
+
+        for (number, line) in source.split('\n').enumerate() {
+            let line_end = line_start + line.len();
+
+            let mut labels = self.labels
+                .iter()
+                .filter(|label| {
+                    let start = label.range.start as usize;
+
+                    // Labels at the very end of the input belong to the last line.
+                    (line_start..line_end).contains(&start) || (start == line_end && line_end == source.len())
+                })
+                .collect::<Vec<_>>();
+
+            if !labels.is_empty() {
+                labels.sort_by_key(|label| label.range.start);
+
+                let gutter = if multiline {
+                    format!("{:>3} | ", number + 1)
+                } else {
+                    "  | ".to_string()
+                };
+
+                let _ = writeln!(out, "{}{}", gutter, line);
+
+                for label in labels {
+                    let start = label.range.start as usize - line_start;
+                    let end = (label.range.end as usize).clamp(start + line_start, line_end) - line_start;
+
+                    let column = line[..start].width();
+                    let width = line[start..end].width().max(1);
+
+                    let (marker, color) = if label.primary {
+                        ('^', Color::Red)
+                    } else {
+                        ('-', Color::Blue)
+                    };
+
+                    let _ = write!(
+                        out,
+                        "{:indent$}{}{}",
+                        "",
+                        SetForegroundColor(color),
+                        marker.to_string().repeat(width),
+                        indent = gutter.len() + column
+                    );
+
+                    if !label.message.is_empty() {
+                        let _ = write!(out, " {}", label.message);
+                    }
+
+                    let _ = writeln!(out, "{}", SetForegroundColor(Color::Reset));
+                }
+            }
+
+            line_start = line_end + 1;
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(
+                out,
+                "  = {}note{}: {}",
+                SetAttribute(Attribute::Bold),
+                SetAttribute(Attribute::Reset),
+                note
+            );
+        }
+
+        for hint in &self.hints {
+            let _ = writeln!(
+                out,
+                "  = {}hint{}: {}",
+                SetForegroundColor(Color::Cyan),
+                SetForegroundColor(Color::Reset),
+                hint
+            );
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Removes the escape sequences of colors and attributes.
+    fn strip_styles(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\u{1b}' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    pub fn test_render() {
+        let diagnostic = Diagnostic::new("Incompatible units")
+            .with_primary(0..11, "")
+            .with_secondary(0..3, "this is a size")
+            .with_secondary(6..11, "this is a duration")
+            .with_hint("convert one of them");
+
+        assert_eq!(
+            strip_styles(&diagnostic.render("1MB + 250ms")),
+            "error: Incompatible units\n  | 1MB + 250ms\n    ^^^^^^^^^^^\n    --- this is a size\n          ----- this is a duration\n  = hint: convert one of them\n"
+        );
+    }
+
+    #[test]
+    pub fn test_render_wide_chars() {
+        use crate::read::buffered::Buffered;
+        use crate::read::bytes::Cursor;
+        use crate::read::lex::Lexer;
+        use crate::read::parse::parse_recovering;
+
+        // The caret is placed by display width, not by byte offset.
+        let input = "'日本' + $ 1";
+        let (_, errors) = parse_recovering(&mut Buffered::new(Lexer::new(Cursor::new(input))));
+        let rendered = strip_styles(&errors[0].value.diagnostic(errors[0].range.clone()).render(input));
+
+        assert_eq!(rendered.lines().nth(2), Some("             ^ not valid here"));
+
+        let diagnostic = Diagnostic::new("Mismatched types").with_primary(1..7, "");
+        assert_eq!(strip_styles(&diagnostic.render("'日本' + 1")).lines().nth(2), Some("     ^^^^"));
+    }
+}
\ No newline at end of file
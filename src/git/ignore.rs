@@ -0,0 +1,168 @@
+//! # The patterns of `.gitignore` files.
+
+/// A pattern from a `.gitignore` file in the directory `base`.
+pub struct Pattern {
+    glob: String,
+    /// The directory of the file relative to the work tree, like `src/`.
+    base: String,
+    negated: bool,
+    directory_only: bool,
+    /// Whether the pattern matches the whole path instead of the file name.
+    anchored: bool,
+}
+
+/// Parses the lines of a `.gitignore` file in `base`, which is empty or ends with `/`.
+pub fn parse_patterns(text: &str, base: &str) -> Vec<Pattern> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+
+            let (directory_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            Some(Pattern {
+                glob: line.trim_start_matches('/').to_string(),
+                base: base.to_string(),
+                negated,
+                directory_only,
+                anchored: line.contains('/'),
+            })
+        })
+        .collect()
+}
+
+/// Returns whether the path, relative to the work tree, is ignored. The last matching
+/// pattern wins, so later patterns should come from deeper directories.
+pub fn is_ignored(patterns: &[Pattern], path: &str, is_directory: bool) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    for pattern in patterns.iter().rev() {
+        let Some(relative) = path.strip_prefix(&pattern.base) else {
+            continue;
+        };
+
+        if pattern.directory_only && !is_directory {
+            continue;
+        }
+
+        let text = if pattern.anchored { relative } else { name };
+
+        if wildmatch(pattern.glob.as_bytes(), text.as_bytes()) {
+            return !pattern.negated;
+        }
+    }
+
+    false
+}
+
+/// Matches `*`, `?`, `[a-z]` and `**`, which also matches slashes.
+pub fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+            let rest = &rest[1..];
+
+            // `**/` also matches no directory at all.
+            if let Some(after) = rest.strip_prefix(b"/") {
+                if wildmatch(after, text) {
+                    return true;
+                }
+            }
+
+            (0..=text.len()).any(|i| wildmatch(rest, &text[i..]))
+        }
+        Some((b'*', rest)) => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| wildmatch(rest, &text[i..])),
+        Some((b'?', rest)) => match text.split_first() {
+            Some((&c, text)) => c != b'/' && wildmatch(rest, text),
+            None => false,
+        },
+        Some((b'[', rest)) => match (text.split_first(), match_class(rest, text.first().copied())) {
+            (Some((_, text)), Some((true, rest))) => wildmatch(rest, text),
+            (_, Some((false, _))) => false,
+            // Without a closing bracket, the bracket is literal.
+            (Some((b'[', text)), None) => wildmatch(rest, text),
+            _ => false,
+        },
+        Some((b'\\', [escaped, rest @ ..])) => {
+            text.first() == Some(escaped) && wildmatch(rest, &text[1..])
+        }
+        Some((&c, rest)) => text.first() == Some(&c) && wildmatch(rest, &text[1..]),
+    }
+}
+
+/// Matches the character against the class after a `[`, and returns the rest of the
+/// pattern. Returns `None` if the class isn't closed.
+fn match_class(class: &[u8], c: Option<u8>) -> Option<(bool, &[u8])> {
+    let (negated, class) = match class.first() {
+        Some(b'!' | b'^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+
+    let mut matched = false;
+    let mut i = 0;
+
+    loop {
+        match class.get(i)? {
+            // A `]` first in the class is literal.
+            b']' if i > 0 => break,
+            &start => {
+                let end = match (class.get(i + 1), class.get(i + 2)) {
+                    (Some(b'-'), Some(&end)) if end != b']' => {
+                        i += 2;
+                        end
+                    }
+                    _ => start,
+                };
+
+                matched |= c.is_some_and(|c| (start..=end).contains(&c));
+                i += 1;
+            }
+        }
+    }
+
+    Some((c.is_some_and(|c| c != b'/') && matched != negated, &class[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_is_ignored() {
+        assert!(wildmatch(b"*.rs", b"main.rs"));
+        assert!(!wildmatch(b"*.rs", b"src/main.rs"));
+        assert!(wildmatch(b"src/**/*.rs", b"src/main.rs"));
+        assert!(wildmatch(b"src/**/*.rs", b"src/git/index.rs"));
+        assert!(wildmatch(b"**/target", b"a/b/target"));
+        assert!(wildmatch(b"file[0-9].txt", b"file7.txt"));
+        assert!(!wildmatch(b"file[!0-9].txt", b"file7.txt"));
+        assert!(wildmatch(b"a[b", b"a[b"));
+        assert!(wildmatch(b"\\*", b"*"));
+        assert!(!wildmatch(b"\\*", b"a"));
+
+        let mut patterns = parse_patterns("# build output\n/target\n*.log\n!keep.log\ndocs/\n", "");
+        patterns.extend(parse_patterns("*.tmp\n", "src/"));
+
+        assert!(is_ignored(&patterns, "target", true));
+        assert!(!is_ignored(&patterns, "src/target", true));
+        assert!(is_ignored(&patterns, "src/debug.log", false));
+        assert!(!is_ignored(&patterns, "keep.log", false));
+        assert!(is_ignored(&patterns, "docs", true));
+        assert!(!is_ignored(&patterns, "docs", false));
+        assert!(is_ignored(&patterns, "src/a/b.tmp", false));
+        assert!(!is_ignored(&patterns, "b.tmp", false));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,134 @@
+//! # The index, which has the staged content and the file times git last saw.
+
+use crate::git::objects::{invalid, ObjectId};
+use std::io;
+
+pub struct IndexEntry {
+    pub path: String,
+    pub mode: u32,
+    pub id: ObjectId,
+    /// The modification time in seconds and nanoseconds.
+    pub mtime: (u32, u32),
+    /// The size of the file, truncated to 32 bits.
+    pub size: u32,
+    /// The merge stage, which is not 0 for conflicts.
+    pub stage: u8,
+    /// Whether the file isn't checked out, in a sparse checkout.
+    pub skip_worktree: bool,
+    /// Whether the file was added with `git add -N`, without content.
+    pub intent_to_add: bool,
+}
+
+const EXTENDED: u16 = 0x4000;
+const SKIP_WORKTREE: u16 = 0x4000;
+const INTENT_TO_ADD: u16 = 0x2000;
+
+/// Parses the entries of an index file of version 2, 3 or 4.
+pub fn parse_index(data: &[u8]) -> io::Result<Vec<IndexEntry>> {
+    if data.get(..4) != Some(b"DIRC".as_slice()) {
+        return Err(invalid("index"));
+    }
+
+    let version = read_u32(data, 4)?;
+    let count = read_u32(data, 8)? as usize;
+
+    if !(2..=4).contains(&version) {
+        return Err(invalid("index version"));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut position = 12;
+    let mut previous_path = Vec::new();
+
+    for _ in 0..count {
+        let start = position;
+        let mtime = (read_u32(data, start + 8)?, read_u32(data, start + 12)?);
+        let mode = read_u32(data, start + 24)?;
+        let size = read_u32(data, start + 36)?;
+        let id = data.get(start + 40..start + 60).ok_or_else(|| invalid("index"))?;
+        let flags = read_u16(data, start + 60)?;
+        position = start + 62;
+
+        let extended_flags = if version >= 3 && flags & EXTENDED != 0 {
+            position += 2;
+            read_u16(data, position - 2)?
+        } else {
+            0
+        };
+
+        // Version 4 writes paths as the length to remove from the previous path and
+        // the rest, without padding.
+        let path = if version == 4 {
+            let (strip, length) = read_offset(&data[position.min(data.len())..])?;
+            position += length;
+
+            let end = find_nul(data, position)?;
+            let mut path = previous_path[..previous_path.len().saturating_sub(strip)].to_vec();
+            path.extend_from_slice(&data[position..end]);
+            position = end + 1;
+            path
+        } else {
+            let end = find_nul(data, position)?;
+            let path = data[position..end].to_vec();
+
+            // Entries are padded with 1 to 8 NULs to a multiple of 8 bytes.
+            position = start + (end - start + 8) / 8 * 8;
+            path
+        };
+
+        entries.push(IndexEntry {
+            path: String::from_utf8_lossy(&path).into_owned(),
+            mode,
+            id: ObjectId(id.try_into().unwrap()),
+            mtime,
+            size,
+            stage: ((flags >> 12) & 3) as u8,
+            skip_worktree: extended_flags & SKIP_WORKTREE != 0,
+            intent_to_add: extended_flags & INTENT_TO_ADD != 0,
+        });
+
+        previous_path = path;
+    }
+
+    Ok(entries)
+}
+
+/// Reads a number in the variable length encoding of git, and returns how many bytes
+/// it took.
+fn read_offset(bytes: &[u8]) -> io::Result<(usize, usize)> {
+    let mut byte = *bytes.first().ok_or_else(|| invalid("index"))?;
+    let mut number = (byte & 0x7f) as usize;
+    let mut length = 1;
+
+    while byte & 0x80 != 0 {
+        byte = *bytes.get(length).ok_or_else(|| invalid("index"))?;
+        number = ((number + 1) << 7) | (byte & 0x7f) as usize;
+        length += 1;
+    }
+
+    Ok((number, length))
+}
+
+#[inline]
+fn find_nul(data: &[u8], from: usize) -> io::Result<usize> {
+    data.get(from..)
+        .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+        .map(|end| from + end)
+        .ok_or_else(|| invalid("index"))
+}
+
+#[inline]
+fn read_u32(data: &[u8], at: usize) -> io::Result<u32> {
+    match data.get(at..at + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes(bytes.try_into().unwrap())),
+        None => Err(invalid("index")),
+    }
+}
+
+#[inline]
+fn read_u16(data: &[u8], at: usize) -> io::Result<u16> {
+    match data.get(at..at + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes(bytes.try_into().unwrap())),
+        None => Err(invalid("index")),
+    }
+}
\ No newline at end of file
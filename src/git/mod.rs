@@ -0,0 +1,565 @@
+//! # The status of git repositories, for the `{git}` prompt segment.
+//!
+//! The status is read from `.git` directly instead of running git: the branch from
+//! `HEAD` and the refs, the distance to the upstream branch from the commits, staged
+//! changes by comparing the index to the tree of `HEAD`, and changes in the work tree by
+//! comparing the files to the index.
+//!
+//! Reading the status of a large repository takes a while, so it is read in a background
+//! thread and cached per repository. The prompt shows the cached status meanwhile, and
+//! is drawn again once the status was read.
+
+mod objects;
+mod index;
+mod ignore;
+
+use crate::git::ignore::{is_ignored, parse_patterns, Pattern};
+use crate::git::index::{parse_index, IndexEntry};
+use crate::git::objects::{invalid, parse_commit, parse_tree, Kind, ObjectId, Objects};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+
+const GITLINK: u32 = 0o160000;
+const SYMLINK: u32 = 0o120000;
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Status {
+    /// The branch, or the abbreviated commit if `HEAD` is detached.
+    pub branch: String,
+    /// How many commits the branch is ahead and behind of its upstream branch.
+    pub ahead: usize,
+    pub behind: usize,
+    pub staged: bool,
+    pub dirty: bool,
+    pub untracked: bool,
+}
+
+impl Display for Status {
+    /// Writes the status like `main ↑1 ↓2 +!?`, with `+` for staged changes, `!` for
+    /// unstaged changes and `?` for untracked files.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.branch)?;
+
+        if self.ahead > 0 {
+            write!(f, " ↑{}", self.ahead)?;
+        }
+
+        if self.behind > 0 {
+            write!(f, " ↓{}", self.behind)?;
+        }
+
+        if self.staged || self.dirty || self.untracked {
+            write!(f, " ")?;
+        }
+
+        for (marker, shown) in [('+', self.staged), ('!', self.dirty), ('?', self.untracked)] {
+            if shown {
+                write!(f, "{}", marker)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Repository {
+    /// The `.git` directory, or the directory of a worktree in it.
+    git_directory: PathBuf,
+    /// The directory with the refs and objects, which worktrees share.
+    common_directory: PathBuf,
+    work_tree: PathBuf,
+}
+
+impl Repository {
+    /// Finds the repository of the directory or its nearest parent.
+    pub fn discover(directory: &Path) -> Option<Self> {
+        for work_tree in directory.ancestors() {
+            let dot_git = work_tree.join(".git");
+
+            let git_directory = if dot_git.is_dir() {
+                dot_git
+            } else if dot_git.is_file() {
+                // Worktrees and submodules have a file that points to the git directory.
+                let text = std::fs::read_to_string(&dot_git).ok()?;
+                work_tree.join(text.strip_prefix("gitdir:")?.trim())
+            } else {
+                continue;
+            };
+
+            let common_directory = match std::fs::read_to_string(git_directory.join("commondir")) {
+                Ok(common) => git_directory.join(common.trim()),
+                Err(_) => git_directory.clone(),
+            };
+
+            return Some(Self {
+                git_directory,
+                common_directory,
+                work_tree: work_tree.to_path_buf(),
+            });
+        }
+
+        None
+    }
+
+    pub fn status(&self) -> io::Result<Status> {
+        let objects = Objects::open(&self.common_directory.join("objects"))?;
+        let head = std::fs::read_to_string(self.git_directory.join("HEAD"))?;
+        let head = head.trim();
+
+        let (branch, head_commit) = match head.strip_prefix("ref: ") {
+            Some(reference) => {
+                let branch = reference.strip_prefix("refs/heads/").unwrap_or(reference);
+                (branch.to_string(), self.resolve(reference))
+            }
+            None => {
+                let id = ObjectId::from_hex(head).ok_or_else(|| invalid("HEAD"))?;
+                (id.to_string()[..7].to_string(), Some(id))
+            }
+        };
+
+        let mut status = Status { branch, ..Status::default() };
+
+        let upstream = head.strip_prefix("ref: refs/heads/")
+            .and_then(|branch| self.upstream(branch))
+            .and_then(|upstream| self.resolve(&upstream));
+
+        if let (Some(local), Some(upstream)) = (head_commit, upstream) {
+            (status.ahead, status.behind) = ahead_behind(&objects, local, upstream)?;
+        }
+
+        let index = match std::fs::read(self.git_directory.join("index")) {
+            Ok(data) => parse_index(&data)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        let mut head_files = HashMap::new();
+
+        if let Some(commit) = head_commit {
+            let (tree, _, _) = parse_commit(&objects.read_kind(commit, Kind::Commit)?)?;
+            read_tree(&objects, tree, "", &mut head_files)?;
+        }
+
+        status.staged = index.len() != head_files.len() || index.iter().any(|entry| {
+            entry.stage != 0 || head_files.get(&entry.path) != Some(&(entry.mode, entry.id))
+        });
+
+        status.dirty = index.iter().any(|entry| self.is_modified(entry));
+
+        let tracked = index.iter().map(|entry| entry.path.as_str()).collect();
+
+        // Later patterns win, so the patterns of the user come before the repository's.
+        let mut patterns = [self.excludes_file(), Some(self.common_directory.join("info").join("exclude"))]
+            .into_iter()
+            .flatten()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .flat_map(|text| parse_patterns(&text, ""))
+            .collect();
+
+        status.untracked = self.has_untracked(&self.work_tree, "", &tracked, &mut patterns);
+        Ok(status)
+    }
+
+    /// Returns the commit of a ref like `refs/heads/main`, from its file or `packed-refs`.
+    fn resolve(&self, reference: &str) -> Option<ObjectId> {
+        if let Ok(text) = std::fs::read_to_string(self.common_directory.join(reference)) {
+            return match text.trim().strip_prefix("ref: ") {
+                Some(target) => self.resolve(target),
+                None => ObjectId::from_hex(text.trim()),
+            };
+        }
+
+        let packed = std::fs::read_to_string(self.common_directory.join("packed-refs")).ok()?;
+
+        packed.lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(_, name)| *name == reference)
+            .and_then(|(id, _)| ObjectId::from_hex(id))
+    }
+
+    /// Returns the ref of the upstream branch from the config, like `refs/remotes/origin/main`.
+    fn upstream(&self, branch: &str) -> Option<String> {
+        let config = std::fs::read_to_string(self.common_directory.join("config")).ok()?;
+        let section = format!("[branch \"{}\"]", branch);
+
+        let mut in_section = false;
+        let mut remote = None;
+        let mut merge = None;
+
+        for line in config.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_section = line == section;
+            } else if let Some((key, value)) = line.split_once('=').filter(|_| in_section) {
+                match key.trim() {
+                    "remote" => remote = Some(value.trim()),
+                    "merge" => merge = Some(value.trim()),
+                    _ => {}
+                }
+            }
+        }
+
+        let merge = merge?;
+
+        match (remote?, merge.strip_prefix("refs/heads/")) {
+            // The upstream of `.` is a local branch.
+            (".", _) => Some(merge.to_string()),
+            (remote, Some(branch)) => Some(format!("refs/remotes/{}/{}", remote, branch)),
+            (_, None) => None,
+        }
+    }
+
+    /// Returns the file with the ignore patterns of the user, from `core.excludesFile` or
+    /// else `git/ignore` in `$XDG_CONFIG_HOME` or `~/.config`.
+    fn excludes_file(&self) -> Option<PathBuf> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".config")));
+
+        // The config of the repository overrides the global ones.
+        let configs = [
+            config_home.as_ref().map(|config_home| config_home.join("git").join("config")),
+            home.as_ref().map(|home| home.join(".gitconfig")),
+            Some(self.common_directory.join("config")),
+        ];
+
+        let configured = configs.into_iter()
+            .flatten()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|text| config_value(&text, "core", "excludesfile"))
+            .next_back();
+
+        match configured {
+            Some(path) => match path.strip_prefix("~/") {
+                Some(rest) => Some(home?.join(rest)),
+                None => Some(PathBuf::from(path)),
+            },
+            None => Some(config_home?.join("git").join("ignore")),
+        }
+    }
+
+    /// Returns whether the file differs from the index. Files with the same size and
+    /// modification time as in the index are taken as unchanged, like git does.
+    fn is_modified(&self, entry: &IndexEntry) -> bool {
+        if entry.mode == GITLINK || entry.skip_worktree {
+            return false;
+        }
+
+        if entry.stage != 0 || entry.intent_to_add {
+            return true;
+        }
+
+        let path = self.work_tree.join(&entry.path);
+
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            return true;
+        };
+
+        // Git writes a size of 0 for files that changed too soon after being added.
+        let same_size = metadata.len() as u32 == entry.size;
+
+        if (!same_size && entry.size != 0) || metadata.is_symlink() != (entry.mode == SYMLINK) {
+            return true;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let executable = metadata.mode() & 0o100 != 0;
+
+            if !metadata.is_symlink() && executable != (entry.mode & 0o100 != 0) {
+                return true;
+            }
+
+            if same_size && (metadata.mtime() as u32, metadata.mtime_nsec() as u32) == entry.mtime {
+                return false;
+            }
+        }
+
+        let content = if metadata.is_symlink() {
+            std::fs::read_link(&path).map(|target| target.to_string_lossy().into_owned().into_bytes())
+        } else {
+            std::fs::read(&path)
+        };
+
+        content.map_or(true, |content| ObjectId::of_blob(&content) != entry.id)
+    }
+
+    /// Returns whether the directory has a file that isn't tracked or ignored.
+    fn has_untracked(&self, directory: &Path, base: &str, tracked: &HashSet<&str>, patterns: &mut Vec<Pattern>) -> bool {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return false;
+        };
+
+        let pattern_count = patterns.len();
+
+        if let Ok(text) = std::fs::read_to_string(directory.join(".gitignore")) {
+            patterns.extend(parse_patterns(&text, base));
+        }
+
+        let untracked = entries.flatten().any(|entry| {
+            let name = entry.file_name();
+            let path = format!("{}{}", base, name.to_string_lossy());
+            let is_directory = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+            if name == ".git" || tracked.contains(path.as_str()) || is_ignored(patterns, &path, is_directory) {
+                return false;
+            }
+
+            // Repositories in the work tree are untracked as a whole.
+            if is_directory && !entry.path().join(".git").exists() {
+                self.has_untracked(&entry.path(), &format!("{}/", path), tracked, patterns)
+            } else {
+                true
+            }
+        });
+
+        patterns.truncate(pattern_count);
+        untracked
+    }
+}
+
+/// Returns the last value of the key in a section without subsection of a git config
+/// file. Section and key names are case-insensitive.
+fn config_value(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    let mut value = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.split_once(']')).map(|(name, _)| name) {
+            in_section = name.trim().eq_ignore_ascii_case(section);
+        } else if let Some((name, text)) = line.split_once('=').filter(|_| in_section) {
+            if name.trim().eq_ignore_ascii_case(key) {
+                let text = text.trim();
+                value = Some(text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text).to_string());
+            }
+        }
+    }
+
+    value
+}
+
+/// Collects the files of the tree and its subtrees by their path.
+fn read_tree(objects: &Objects, tree: ObjectId, base: &str, files: &mut HashMap<String, (u32, ObjectId)>) -> io::Result<()> {
+    let data = objects.read_kind(tree, Kind::Tree)?;
+
+    for (mode, name, id) in parse_tree(&data)? {
+        let path = format!("{}{}", base, String::from_utf8_lossy(name));
+
+        if mode == 0o40000 {
+            read_tree(objects, id, &format!("{}/", path), files)?;
+        } else {
+            files.insert(path, (mode, id));
+        }
+    }
+
+    Ok(())
+}
+
+const LOCAL: u8 = 1;
+const UPSTREAM: u8 = 2;
+const BOTH: u8 = LOCAL | UPSTREAM;
+/// Marks commits that are in the queue, so that each is queued once.
+const QUEUED: u8 = 4;
+
+/// Counts the commits only reachable from `local` and only from `upstream`.
+///
+/// Commits are visited newest first and marked with where they are reachable from,
+/// until all commits left to visit are reachable from both.
+fn ahead_behind(objects: &Objects, local: ObjectId, upstream: ObjectId) -> io::Result<(usize, usize)> {
+    let mut walk = Walk { marks: HashMap::new(), queue: BinaryHeap::new(), interesting: 0 };
+
+    walk.mark(objects, local, LOCAL)?;
+    walk.mark(objects, upstream, UPSTREAM)?;
+
+    while walk.interesting > 0 {
+        let Some((_, id)) = walk.queue.pop() else {
+            break;
+        };
+
+        let mark = walk.marks.get_mut(&id).map_or(0, |mark| {
+            *mark &= !QUEUED;
+            *mark
+        });
+
+        if mark != BOTH {
+            walk.interesting -= 1;
+        }
+
+        let (_, parents, _) = parse_commit(&objects.read_kind(id, Kind::Commit)?)?;
+
+        for parent in parents {
+            walk.mark(objects, parent, mark)?;
+        }
+    }
+
+    let count = |wanted| walk.marks.values().filter(|&&mark| mark & BOTH == wanted).count();
+    Ok((count(LOCAL), count(UPSTREAM)))
+}
+
+struct Walk {
+    marks: HashMap<ObjectId, u8>,
+    /// The commits to visit by their time.
+    queue: BinaryHeap<(i64, ObjectId)>,
+    /// How many queued commits aren't reachable from both yet.
+    interesting: usize,
+}
+
+impl Walk {
+    /// Adds the mark to the commit, and queues the commit if the mark is new to it.
+    fn mark(&mut self, objects: &Objects, id: ObjectId, mark: u8) -> io::Result<()> {
+        let old = self.marks.get(&id).copied().unwrap_or(0);
+        let new = old | mark;
+
+        if new == old {
+            return Ok(());
+        }
+
+        if old & QUEUED != 0 {
+            if new & BOTH == BOTH {
+                self.interesting -= 1;
+            }
+        } else {
+            let (_, _, time) = parse_commit(&objects.read_kind(id, Kind::Commit)?)?;
+            self.queue.push((time, id));
+
+            if new & BOTH != BOTH {
+                self.interesting += 1;
+            }
+        }
+
+        self.marks.insert(id, new | QUEUED);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Cached {
+    status: Option<Status>,
+    refreshing: bool,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<PathBuf, Cached>>> = LazyLock::new(Default::default);
+
+/// Locks the cache, which stays usable if a thread panicked while holding it.
+fn lock_cache() -> MutexGuard<'static, HashMap<PathBuf, Cached>> {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the last status of the repository of the directory, and whether it is being
+/// read again. Returns `None` if there is no repository.
+///
+/// With `refresh`, the status is read again in the background, since it may have
+/// changed, unless that is happening already.
+pub fn cached_status(directory: &Path, refresh: bool) -> Option<(Option<Status>, bool)> {
+    let repository = Repository::discover(directory)?;
+    let mut cache = lock_cache();
+    let cached = cache.entry(repository.work_tree.clone()).or_default();
+
+    if refresh && !cached.refreshing {
+        cached.refreshing = true;
+
+        std::thread::spawn(move || {
+            // A status that can't be read isn't shown, also if reading it panics, so that
+            // the prompt doesn't wait for it.
+            let status = std::panic::catch_unwind(|| repository.status().ok()).ok().flatten();
+            let mut cache = lock_cache();
+            let cached = cache.entry(repository.work_tree).or_default();
+
+            cached.status = status;
+            cached.refreshing = false;
+        });
+    }
+
+    Some((cached.status.clone(), cached.refreshing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(directory: &Path, arguments: &[&str]) -> bool {
+        Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "init.defaultBranch=main"])
+            .args(arguments)
+            .current_dir(directory)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    #[test]
+    pub fn test_config_value() {
+        let config = "[core]\n\tbare = false\n[Core]\n\tExcludesFile = \"~/.ignore\"\n[branch \"main\"]\n\tremote = origin\n";
+
+        assert_eq!(config_value(config, "core", "excludesfile"), Some("~/.ignore".to_string()));
+        assert_eq!(config_value(config, "core", "bare"), Some("false".to_string()));
+        assert_eq!(config_value(config, "core", "remote"), None);
+    }
+
+    #[test]
+    pub fn test_status() {
+        // The status is compared to what git does.
+        if !Command::new("git").arg("--version").output().is_ok_and(|output| output.status.success()) {
+            println!("Skipping test_status, git isn't installed");
+            return;
+        }
+
+        let directory = std::env::temp_dir().join(format!("shesh-git-{}", std::process::id()));
+        let excludes = std::env::temp_dir().join(format!("shesh-git-{}-ignore", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("src")).unwrap();
+        assert!(git(&directory, &["init", "-q"]));
+
+        let status = |directory: &Path| Repository::discover(directory).unwrap().status().unwrap();
+
+        std::fs::write(directory.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(directory.join(".gitignore"), "/target\n").unwrap();
+        std::fs::create_dir(directory.join("target")).unwrap();
+        std::fs::write(directory.join("target").join("shesh"), "").unwrap();
+
+        assert_eq!(status(&directory.join("src")), Status {
+            branch: "main".to_string(),
+            untracked: true,
+            ..Status::default()
+        });
+
+        assert!(git(&directory, &["add", "."]));
+        assert_eq!(status(&directory).to_string(), "main +");
+
+        assert!(git(&directory, &["commit", "-q", "-m", "Initial commit"]));
+        assert!(git(&directory, &["branch", "upstream"]));
+        assert!(git(&directory, &["branch", "--set-upstream-to", "upstream"]));
+
+        // Files ignored by the user aren't untracked.
+        std::fs::write(&excludes, "*.bak\n").unwrap();
+        std::fs::write(directory.join("notes.bak"), "").unwrap();
+        assert!(git(&directory, &["config", "core.excludesFile", &excludes.display().to_string()]));
+
+        for message in ["First", "Second"] {
+            std::fs::write(directory.join("src").join("main.rs"), message).unwrap();
+            assert!(git(&directory, &["commit", "-q", "-a", "-m", message]));
+        }
+
+        // Packed objects are read like loose ones.
+        assert!(git(&directory, &["gc", "-q"]));
+        std::fs::write(directory.join("src").join("main.rs"), "Third").unwrap();
+
+        assert_eq!(status(&directory).to_string(), "main ↑2 !");
+        assert!(git(&directory, &["checkout", "-q", "--", "src"]));
+
+        assert!(git(&directory, &["checkout", "-q", "upstream"]));
+        assert!(git(&directory, &["branch", "--set-upstream-to", "main"]));
+        assert_eq!(status(&directory).to_string(), "upstream ↓2");
+
+        assert!(git(&directory, &["checkout", "-q", "--detach"]));
+        assert_eq!(status(&directory).branch.len(), 7);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        std::fs::remove_file(&excludes).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,411 @@
+//! # Reading objects from the object database, loose or in packs.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Debug)]
+pub struct ObjectId(pub [u8; 20]);
+
+impl ObjectId {
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.as_bytes();
+
+        if hex.len() != 40 {
+            return None;
+        }
+
+        let mut id = [0; 20];
+
+        for (byte, pair) in id.iter_mut().zip(hex.chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+        }
+
+        Some(Self(id))
+    }
+
+    /// The id of the blob with the content, which is how git hashes files.
+    pub fn of_blob(content: &[u8]) -> Self {
+        let mut hasher = sha1_smol::Sha1::new();
+        hasher.update(format!("blob {}\0", content.len()).as_bytes());
+        hasher.update(content);
+
+        Self(hasher.digest().bytes())
+    }
+}
+
+impl Display for ObjectId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Kind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl Kind {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"commit" => Some(Self::Commit),
+            b"tree" => Some(Self::Tree),
+            b"blob" => Some(Self::Blob),
+            b"tag" => Some(Self::Tag),
+            _ => None,
+        }
+    }
+
+    fn from_pack_type(pack_type: u8) -> Option<Self> {
+        match pack_type {
+            1 => Some(Self::Commit),
+            2 => Some(Self::Tree),
+            3 => Some(Self::Blob),
+            4 => Some(Self::Tag),
+            _ => None,
+        }
+    }
+}
+
+const OFS_DELTA: u8 = 6;
+const REF_DELTA: u8 = 7;
+
+pub struct Objects {
+    directory: Box<Path>,
+    packs: Vec<Pack>,
+}
+
+impl Objects {
+    /// Opens the `objects` directory. Packs are opened now, and their objects are read
+    /// from the files when they are needed.
+    pub fn open(directory: &Path) -> io::Result<Self> {
+        let mut packs = Vec::new();
+
+        if let Ok(entries) = std::fs::read_dir(directory.join("pack")) {
+            for entry in entries {
+                let path = entry?.path();
+
+                if path.extension().is_some_and(|extension| extension == "idx") {
+                    packs.push(Pack::open(&path, &path.with_extension("pack"))?);
+                }
+            }
+        }
+
+        Ok(Self {
+            directory: directory.into(),
+            packs,
+        })
+    }
+
+    pub fn read(&self, id: ObjectId) -> io::Result<(Kind, Vec<u8>)> {
+        for pack in &self.packs {
+            if let Some(offset) = pack.find(id) {
+                return pack.read(self, offset);
+            }
+        }
+
+        let hex = id.to_string();
+        let compressed = std::fs::read(self.directory.join(&hex[..2]).join(&hex[2..]))?;
+        let data = inflate(&compressed)?;
+
+        let header_end = data.iter().position(|&byte| byte == 0).ok_or_else(|| invalid("object header"))?;
+        let kind = data[..header_end].split(|&byte| byte == b' ').next().and_then(Kind::from_name);
+
+        match kind {
+            Some(kind) => Ok((kind, data[header_end + 1..].to_vec())),
+            None => Err(invalid("object kind")),
+        }
+    }
+
+    /// Reads the object, and fails if it has another kind.
+    pub fn read_kind(&self, id: ObjectId, kind: Kind) -> io::Result<Vec<u8>> {
+        match self.read(id)? {
+            (other, data) if other == kind => Ok(data),
+            _ => Err(invalid("object kind")),
+        }
+    }
+}
+
+/// A pack file with its index. Only version 2 of the index is supported, which git
+/// writes since 1.5.2.
+struct Pack {
+    file: File,
+    ids: Vec<ObjectId>,
+    offsets: Vec<u64>,
+    /// The offsets of all objects in order, to find where each one ends.
+    sorted_offsets: Vec<u64>,
+    end: u64,
+}
+
+impl Pack {
+    fn open(index_path: &Path, pack_path: &Path) -> io::Result<Self> {
+        let index = std::fs::read(index_path)?;
+
+        if index.get(..8) != Some(b"\xfftOc\0\0\0\x02".as_slice()) {
+            return Err(invalid("pack index version"));
+        }
+
+        let count = read_u32(&index, 8 + 255 * 4)? as usize;
+        let ids_start = 8 + 256 * 4;
+        let offsets_start = ids_start + count * 24;
+        let large_offsets_start = offsets_start + count * 4;
+
+        let ids = (0..count)
+            .map(|i| {
+                let bytes = index.get(ids_start + i * 20..ids_start + i * 20 + 20).ok_or_else(|| invalid("pack index"))?;
+                Ok(ObjectId(bytes.try_into().unwrap()))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let offsets = (0..count)
+            .map(|i| {
+                let offset = read_u32(&index, offsets_start + i * 4)?;
+
+                // Offsets past 2 GiB are in a table of 8 byte offsets.
+                if offset & 0x8000_0000 == 0 {
+                    return Ok(offset as u64);
+                }
+
+                let at = large_offsets_start + (offset & 0x7fff_ffff) as usize * 8;
+                Ok(((read_u32(&index, at)? as u64) << 32) | read_u32(&index, at + 4)? as u64)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let file = File::open(pack_path)?;
+
+        // The pack ends with a checksum.
+        let end = file.metadata()?.len().saturating_sub(20);
+
+        let mut sorted_offsets = offsets.clone();
+        sorted_offsets.sort_unstable();
+
+        Ok(Self { file, ids, offsets, sorted_offsets, end })
+    }
+
+    fn find(&self, id: ObjectId) -> Option<u64> {
+        self.ids.binary_search(&id).ok().map(|i| self.offsets[i])
+    }
+
+    fn read(&self, objects: &Objects, offset: u64) -> io::Result<(Kind, Vec<u8>)> {
+        let end = match self.sorted_offsets.binary_search(&offset) {
+            Ok(i) => self.sorted_offsets.get(i + 1).copied().unwrap_or(self.end),
+            Err(_) => return Err(invalid("pack offset")),
+        };
+
+        let mut entry = vec![0; end.saturating_sub(offset) as usize];
+        (&self.file).seek(SeekFrom::Start(offset))?;
+        (&self.file).read_exact(&mut entry)?;
+
+        // The header has the type and the size, 4 bits and then 7 bits per byte.
+        let mut position = 0;
+        let mut byte = *entry.first().ok_or_else(|| invalid("pack entry"))?;
+        let pack_type = (byte >> 4) & 7;
+
+        while byte & 0x80 != 0 {
+            position += 1;
+            byte = *entry.get(position).ok_or_else(|| invalid("pack entry"))?;
+        }
+
+        position += 1;
+
+        match pack_type {
+            OFS_DELTA => {
+                let mut byte = *entry.get(position).ok_or_else(|| invalid("pack entry"))?;
+                let mut distance = (byte & 0x7f) as u64;
+
+                while byte & 0x80 != 0 {
+                    position += 1;
+                    byte = *entry.get(position).ok_or_else(|| invalid("pack entry"))?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+                }
+
+                let base_offset = offset.checked_sub(distance).ok_or_else(|| invalid("pack entry"))?;
+                let (kind, base) = self.read(objects, base_offset)?;
+
+                Ok((kind, apply_delta(&base, &inflate(&entry[position + 1..])?)?))
+            }
+            REF_DELTA => {
+                let base_id = entry.get(position..position + 20).ok_or_else(|| invalid("pack entry"))?;
+                let (kind, base) = objects.read(ObjectId(base_id.try_into().unwrap()))?;
+
+                Ok((kind, apply_delta(&base, &inflate(&entry[position + 20..])?)?))
+            }
+            pack_type => match Kind::from_pack_type(pack_type) {
+                Some(kind) => Ok((kind, inflate(&entry[position..])?)),
+                None => Err(invalid("pack entry type")),
+            },
+        }
+    }
+}
+
+/// Builds an object from its base and a delta, which copies parts of the base and
+/// inserts new data.
+fn apply_delta(base: &[u8], delta: &[u8]) -> io::Result<Vec<u8>> {
+    let mut position = 0;
+
+    let mut size = || {
+        let mut size = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = *delta.get(position).ok_or_else(|| invalid("delta"))?;
+            position += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Ok::<_, io::Error>(size);
+            }
+        }
+    };
+
+    let _base_size = size()?;
+    let target_size = size()?;
+    let mut target = Vec::with_capacity(target_size);
+
+    while let Some(&command) = delta.get(position) {
+        position += 1;
+
+        if command & 0x80 != 0 {
+            // The bits tell which bytes of the offset and the size follow.
+            let mut number = |bits: u8, first_bit: u8| {
+                let mut number = 0;
+
+                for bit in 0..bits {
+                    if command & (1 << (first_bit + bit)) != 0 {
+                        number |= (*delta.get(position).ok_or_else(|| invalid("delta"))? as usize) << (bit * 8);
+                        position += 1;
+                    }
+                }
+
+                Ok::<_, io::Error>(number)
+            };
+
+            let offset = number(4, 0)?;
+            let size = match number(3, 4)? {
+                0 => 0x10000,
+                size => size,
+            };
+
+            target.extend_from_slice(base.get(offset..offset + size).ok_or_else(|| invalid("delta"))?);
+        } else if command != 0 {
+            let data = delta.get(position..position + command as usize).ok_or_else(|| invalid("delta"))?;
+            target.extend_from_slice(data);
+            position += command as usize;
+        } else {
+            return Err(invalid("delta"));
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(invalid("delta"));
+    }
+
+    Ok(target)
+}
+
+#[inline]
+fn inflate(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_to_vec_zlib(compressed).map_err(|_| invalid("compressed object"))
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], at: usize) -> io::Result<u32> {
+    match bytes.get(at..at + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes(bytes.try_into().unwrap())),
+        None => Err(invalid("pack index")),
+    }
+}
+
+#[inline]
+pub fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}", what))
+}
+
+/// Returns the tree and the parents of a commit, and its time in seconds.
+pub fn parse_commit(data: &[u8]) -> io::Result<(ObjectId, Vec<ObjectId>, i64)> {
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut time = 0;
+
+    for line in data.split(|&byte| byte == b'\n').take_while(|line| !line.is_empty()) {
+        let line = String::from_utf8_lossy(line);
+
+        if let Some(id) = line.strip_prefix("tree ") {
+            tree = ObjectId::from_hex(id);
+        } else if let Some(id) = line.strip_prefix("parent ") {
+            parents.push(ObjectId::from_hex(id).ok_or_else(|| invalid("commit"))?);
+        } else if let Some(committer) = line.strip_prefix("committer ") {
+            // The time follows the email, before the time zone.
+            time = committer.rsplit(' ').nth(1).and_then(|time| time.parse().ok()).unwrap_or(0);
+        }
+    }
+
+    Ok((tree.ok_or_else(|| invalid("commit"))?, parents, time))
+}
+
+/// Returns the entries of a tree as the mode, the name and the id.
+pub fn parse_tree(data: &[u8]) -> io::Result<Vec<(u32, &[u8], ObjectId)>> {
+    let mut entries = Vec::new();
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        // The mode ends with a space and the name with a NUL.
+        let nul = rest.iter().position(|&byte| byte == 0).ok_or_else(|| invalid("tree"))?;
+        let space = rest[..nul].iter().position(|&byte| byte == b' ').ok_or_else(|| invalid("tree"))?;
+        let id = rest.get(nul + 1..nul + 21).ok_or_else(|| invalid("tree"))?;
+
+        let mode = std::str::from_utf8(&rest[..space]).ok()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .ok_or_else(|| invalid("tree"))?;
+
+        entries.push((mode, &rest[space + 1..nul], ObjectId(id.try_into().unwrap())));
+        rest = &rest[nul + 21..];
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_apply_delta() {
+        let base = b"let x = 1\nlet y = 2\n";
+
+        // Copies 10 bytes from 0, inserts "let z = 3\n", copies 10 bytes from 10.
+        let mut delta = vec![20, 30, 0x90, 10];
+        delta.push(10);
+        delta.extend_from_slice(b"let z = 3\n");
+        delta.extend_from_slice(&[0x91, 10, 10]);
+
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"let x = 1\nlet z = 3\nlet y = 2\n");
+        assert!(apply_delta(base, &[20, 30, 0x91, 15, 10]).is_err());
+
+        let id = ObjectId::of_blob(b"hello\n");
+        assert_eq!(id.to_string(), "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert_eq!(ObjectId::from_hex(&id.to_string()), Some(id));
+    }
+
+    #[test]
+    pub fn test_parse_tree() {
+        let mut data = b"100644 main.rs\0".to_vec();
+        data.extend_from_slice(&[7; 20]);
+        data.extend_from_slice(b"40000 src\0");
+        data.extend_from_slice(&[9; 20]);
+
+        let entries = parse_tree(&data).unwrap();
+        assert_eq!(entries, [(0o100644, &b"main.rs"[..], ObjectId([7; 20])), (0o40000, &b"src"[..], ObjectId([9; 20]))]);
+
+        let mut data = b"100644\0 main.rs".to_vec();
+        data.extend_from_slice(&[7; 20]);
+        assert!(parse_tree(&data).is_err());
+        assert!(parse_tree(b"100644 main.rs\0short").is_err());
+    }
+}
\ No newline at end of file
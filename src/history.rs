@@ -0,0 +1,119 @@
+//! # Inputs that were run, to browse them and to suggest them while typing.
+
+use crate::text_box::{chars_of_word, is_word};
+use std::path::{Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+
+pub struct Entry {
+    pub text: String,
+    pub char_count: usize,
+    /// The working directory the input ran in.
+    pub directory: Option<PathBuf>,
+    pub succeeded: bool,
+}
+
+/// The entries, the newest last.
+pub struct History {
+    entries: Vec<Entry>,
+}
+
+impl History {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn push(&mut self, entry: Entry) {
+        self.entries.push(entry);
+    }
+
+    /// Marks the newest entry as failed, so that it isn't suggested.
+    pub fn mark_failed(&mut self) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.succeeded = false;
+        }
+    }
+
+    /// Returns the rest of the newest entry that continues the input. Entries that ran
+    /// in the `directory` come first, and entries that failed are skipped.
+    pub fn suggest(&self, input: &str, directory: Option<&Path>) -> Option<&str> {
+        if input.is_empty() {
+            return None;
+        }
+
+        let mut candidates = self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.succeeded && entry.text.len() > input.len() && entry.text.starts_with(input));
+
+        let entry = match directory {
+            Some(directory) => candidates
+                .clone()
+                .find(|entry| entry.directory.as_deref() == Some(directory))
+                .or_else(|| candidates.next()),
+            None => candidates.next(),
+        }?;
+
+        Some(&entry.text[input.len()..])
+    }
+
+    /// Returns the index of the newest entry before `before` that contains the query. The
+    /// entry at `before` is skipped where it appears again, to find a different input.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let shown = self.entries.get(before).map(|entry| entry.text.as_str());
+
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.text.contains(query) && Some(entry.text.as_str()) != shown)
+    }
+}
+
+/// Returns the start of the suggestion up to the end of its next word.
+pub fn next_word(suggestion: &str) -> &str {
+    let chars = chars_of_word(suggestion.graphemes(true), is_word);
+
+    suggestion.char_indices().nth(chars).map_or(suggestion, |(byte, _)| &suggestion[..byte])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, directory: &str, succeeded: bool) -> Entry {
+        Entry {
+            text: text.to_string(),
+            char_count: text.chars().count(),
+            directory: Some(PathBuf::from(directory)),
+            succeeded,
+        }
+    }
+
+    #[test]
+    pub fn test_suggest() {
+        let mut history = History::new();
+        history.push(entry("ls src | length", "/crate", true));
+        history.push(entry("ls src/read", "/home", true));
+        history.push(entry("ls src/rea", "/crate", false));
+
+        assert_eq!(history.suggest("ls s", Some(Path::new("/crate"))), Some("rc | length"));
+        assert_eq!(history.suggest("ls s", Some(Path::new("/tmp"))), Some("rc/read"));
+        assert_eq!(history.suggest("ls src/read", None), None);
+        assert_eq!(history.suggest("", None), None);
+
+        assert_eq!(history.search("src", 3), Some(2));
+        assert_eq!(history.search("src", 2), Some(1));
+        assert_eq!(history.search("length", 1), Some(0));
+        assert_eq!(history.search("length", 0), None);
+        assert_eq!(history.search("", 3), Some(2));
+
+        assert_eq!(next_word("rc | length"), "rc");
+        assert_eq!(next_word(" | length"), " | length");
+        assert_eq!(next_word("/read"), "/read");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,269 @@
+//! # Bindings of keys to the actions of the line editor.
+//!
+//! Keys are written like `ctrl-w`, `alt-enter` or `shift-left`, and actions by the
+//! names in [ACTIONS]. The defaults are emacs-like and can be changed in the config file.
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Types the character, which is what unbound keys without Control or Alt do.
+    Insert(char),
+    MoveLeft,
+    MoveRight,
+    /// Moves a line up, or to the previous history entry from the first line.
+    MoveUp,
+    /// Moves a line down, or to the next history entry from the last line.
+    MoveDown,
+    MoveWordLeft,
+    MoveWordRight,
+    MoveToLineStart,
+    MoveToLineEnd,
+    SelectLeft,
+    SelectRight,
+    SelectUp,
+    SelectDown,
+    SelectToLineStart,
+    SelectToLineEnd,
+    DeleteLeft,
+    DeleteRight,
+    KillWordLeft,
+    KillWordRight,
+    KillToLineStart,
+    KillToLineEnd,
+    Yank,
+    /// Replaces the text that was just yanked with the kill before it.
+    YankPop,
+    Transpose,
+    Undo,
+    Redo,
+    /// Copies the selection to the kill ring and the system clipboard.
+    Copy,
+    /// Cuts the selection like [Action::Copy], or kills the word left without one.
+    Cut,
+    ClearScreen,
+    InsertNewline,
+    /// Runs the input, or continues it in a new line if it is incomplete.
+    AcceptLine,
+    /// Completes the file name before the cursor.
+    Complete,
+    /// Replaces the input with the newest earlier input that contains it, and with older
+    /// ones when pressed again.
+    HistorySearch,
+}
+
+/// The names of the actions for the config file.
+pub const ACTIONS: &[(&str, Action)] = &[
+    ("move-left", Action::MoveLeft),
+    ("move-right", Action::MoveRight),
+    ("move-up", Action::MoveUp),
+    ("move-down", Action::MoveDown),
+    ("move-word-left", Action::MoveWordLeft),
+    ("move-word-right", Action::MoveWordRight),
+    ("move-to-line-start", Action::MoveToLineStart),
+    ("move-to-line-end", Action::MoveToLineEnd),
+    ("select-left", Action::SelectLeft),
+    ("select-right", Action::SelectRight),
+    ("select-up", Action::SelectUp),
+    ("select-down", Action::SelectDown),
+    ("select-to-line-start", Action::SelectToLineStart),
+    ("select-to-line-end", Action::SelectToLineEnd),
+    ("delete-left", Action::DeleteLeft),
+    ("delete-right", Action::DeleteRight),
+    ("kill-word-left", Action::KillWordLeft),
+    ("kill-word-right", Action::KillWordRight),
+    ("kill-to-line-start", Action::KillToLineStart),
+    ("kill-to-line-end", Action::KillToLineEnd),
+    ("yank", Action::Yank),
+    ("yank-pop", Action::YankPop),
+    ("transpose", Action::Transpose),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("copy", Action::Copy),
+    ("cut", Action::Cut),
+    ("clear-screen", Action::ClearScreen),
+    ("insert-newline", Action::InsertNewline),
+    ("accept-line", Action::AcceptLine),
+    ("complete", Action::Complete),
+    ("history-search", Action::HistorySearch),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS.iter().find(|(other, _)| *other == name).map(|(_, action)| *action)
+    }
+}
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("left", Action::MoveLeft),
+    ("right", Action::MoveRight),
+    ("up", Action::MoveUp),
+    ("down", Action::MoveDown),
+    ("home", Action::MoveToLineStart),
+    ("end", Action::MoveToLineEnd),
+    ("ctrl-b", Action::MoveLeft),
+    ("ctrl-f", Action::MoveRight),
+    ("ctrl-a", Action::MoveToLineStart),
+    ("ctrl-e", Action::MoveToLineEnd),
+    ("alt-b", Action::MoveWordLeft),
+    ("alt-f", Action::MoveWordRight),
+    ("shift-left", Action::SelectLeft),
+    ("shift-right", Action::SelectRight),
+    ("shift-up", Action::SelectUp),
+    ("shift-down", Action::SelectDown),
+    ("shift-home", Action::SelectToLineStart),
+    ("shift-end", Action::SelectToLineEnd),
+    ("backspace", Action::DeleteLeft),
+    ("delete", Action::DeleteRight),
+    ("ctrl-w", Action::Cut),
+    ("alt-w", Action::Copy),
+    ("alt-d", Action::KillWordRight),
+    ("ctrl-u", Action::KillToLineStart),
+    ("ctrl-k", Action::KillToLineEnd),
+    ("ctrl-y", Action::Yank),
+    ("alt-y", Action::YankPop),
+    ("ctrl-t", Action::Transpose),
+    // Terminals send Ctrl-_ as Ctrl-7.
+    ("ctrl-_", Action::Undo),
+    ("ctrl-7", Action::Undo),
+    ("ctrl-z", Action::Undo),
+    ("alt-_", Action::Redo),
+    ("ctrl-l", Action::ClearScreen),
+    ("alt-enter", Action::InsertNewline),
+    ("enter", Action::AcceptLine),
+    ("tab", Action::Complete),
+    ("ctrl-r", Action::HistorySearch),
+];
+
+pub struct Keymap {
+    bindings: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl Keymap {
+    /// Binds the key, like `ctrl-w`, to the action. Returns `false` if the key is invalid.
+    pub fn bind(&mut self, key: &str, action: Action) -> bool {
+        let Some(key) = parse_key(key) else {
+            return false;
+        };
+
+        self.bindings.insert(key, action);
+        true
+    }
+
+    /// Returns the action of the key. Unbound characters are typed, unless they come with
+    /// Control or Alt, but not both, since AltGr is reported as both.
+    pub fn action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        if let Some(action) = self.bindings.get(&normalize(code, modifiers)) {
+            return Some(*action);
+        }
+
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) == modifiers.contains(KeyModifiers::ALT) => {
+                Some(Action::Insert(c))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self { bindings: HashMap::new() };
+
+        for (key, action) in DEFAULT_BINDINGS {
+            keymap.bind(key, *action);
+        }
+
+        keymap
+    }
+}
+
+/// Parses a key like `ctrl-alt-x`, `shift-left` or `f5`. Modifiers come first, and
+/// the key is a single character or a name like `enter`.
+pub fn parse_key(key: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = key;
+
+    // The last part is the key, even if it is a `-`.
+    while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" | "meta" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+
+        rest = key;
+    }
+
+    let mut chars = rest.chars();
+
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+
+    Some(normalize(code, modifiers))
+}
+
+/// Makes keys comparable to what the terminal reports. Characters already tell whether
+/// Shift was pressed, and come lowercase with Control.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(c) => {
+            let c = if modifiers.contains(KeyModifiers::CONTROL) { c.to_ascii_lowercase() } else { c };
+            (KeyCode::Char(c), modifiers - KeyModifiers::SHIFT)
+        }
+        // Shift-Tab is reported as a key of its own.
+        KeyCode::BackTab => (KeyCode::Tab, modifiers | KeyModifiers::SHIFT),
+        code => (code, modifiers),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_keymap() {
+        assert_eq!(parse_key("ctrl-w"), Some((KeyCode::Char('w'), KeyModifiers::CONTROL)));
+        assert_eq!(parse_key("Ctrl-Alt-W"), Some((KeyCode::Char('w'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert_eq!(parse_key("alt--"), Some((KeyCode::Char('-'), KeyModifiers::ALT)));
+        assert_eq!(parse_key("shift-left"), Some((KeyCode::Left, KeyModifiers::SHIFT)));
+        assert_eq!(parse_key("f12"), Some((KeyCode::F(12), KeyModifiers::NONE)));
+        assert_eq!(parse_key("hyper-x"), None);
+        assert_eq!(parse_key("fx"), None);
+
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.action(KeyCode::Char('w'), KeyModifiers::CONTROL), Some(Action::Cut));
+        assert_eq!(keymap.action(KeyCode::Char('B'), KeyModifiers::SHIFT), Some(Action::Insert('B')));
+        assert_eq!(keymap.action(KeyCode::Char('@'), KeyModifiers::CONTROL | KeyModifiers::ALT), Some(Action::Insert('@')));
+        assert_eq!(keymap.action(KeyCode::Char('q'), KeyModifiers::CONTROL), None);
+
+        assert!(keymap.bind("alt-backspace", Action::KillWordLeft));
+        assert!(keymap.bind("ctrl-q", Action::Undo));
+        assert_eq!(keymap.action(KeyCode::Backspace, KeyModifiers::ALT), Some(Action::KillWordLeft));
+        assert_eq!(keymap.action(KeyCode::Char('q'), KeyModifiers::CONTROL), Some(Action::Undo));
+        assert_eq!(Action::from_name("kill-word-left"), Some(Action::KillWordLeft));
+        assert_eq!(Action::from_name("history-search"), Some(Action::HistorySearch));
+        assert_eq!(keymap.action(KeyCode::Tab, KeyModifiers::NONE), Some(Action::Complete));
+    }
+}
\ No newline at end of file
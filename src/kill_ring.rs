@@ -0,0 +1,83 @@
+//! # Text removed by kill commands, to be yanked back.
+
+/// How many killed texts are kept.
+const CAPACITY: usize = 32;
+
+/// The most recently killed texts, the newest last.
+pub struct KillRing {
+    entries: Vec<String>,
+    /// The entry yanked last, counted back from the newest.
+    yank_index: usize,
+}
+
+impl KillRing {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            yank_index: 0,
+        }
+    }
+
+    /// Adds killed text. If the kill directly follows another one, the text is added
+    /// to the last entry instead, in front of it if the kill was `backward`, so that
+    /// killing several words yanks them back together.
+    pub fn kill(&mut self, text: String, continues: bool, backward: bool) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.entries.last_mut() {
+            Some(last) if continues => {
+                if backward {
+                    last.insert_str(0, &text);
+                } else {
+                    last.push_str(&text);
+                }
+            }
+            _ => {
+                if self.entries.len() == CAPACITY {
+                    self.entries.remove(0);
+                }
+
+                self.entries.push(text);
+            }
+        }
+    }
+
+    /// Returns the newest entry.
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.last().map(String::as_str)
+    }
+
+    /// Returns the entry before the one yanked last, or the newest after the oldest.
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+
+        Some(&self.entries[self.entries.len() - 1 - self.yank_index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_kill_ring() {
+        let mut kill_ring = KillRing::new();
+        assert_eq!(kill_ring.yank(), None);
+
+        kill_ring.kill("ls".to_string(), false, false);
+        kill_ring.kill("size".to_string(), false, true);
+        kill_ring.kill("sort-by ".to_string(), true, true);
+
+        assert_eq!(kill_ring.yank(), Some("sort-by size"));
+        assert_eq!(kill_ring.yank_pop(), Some("ls"));
+        assert_eq!(kill_ring.yank_pop(), Some("sort-by size"));
+    }
+}
\ No newline at end of file
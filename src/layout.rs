@@ -0,0 +1,158 @@
+//! # Positions of the input on the terminal.
+
+use crate::prompt::CONTINUATION_PROMPT;
+use crossterm::style::{Attribute, SetAttribute};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The distance between tab stops. Tabs are written as spaces up to the next stop.
+const TAB_WIDTH: u16 = 8;
+
+/// Where the input is drawn, as columns and rows relative to the row of the prompt.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub cursor: (u16, u16),
+    /// The position after the last character. The column is `columns` if the last row
+    /// is full, because the terminal only wraps when the next character is written.
+    pub end: (u16, u16),
+}
+
+impl Layout {
+    /// Lays out the input with the cursor between `left` and `right`. The first line
+    /// starts after the prompt, the following lines after the [CONTINUATION_PROMPT],
+    /// and lines that are wider than `columns` wrap. Widths are measured per grapheme
+    /// cluster, so wide characters take up two columns and combining marks none.
+    pub fn new(left: &str, right: &str, prompt_width: u16, columns: u16) -> Self {
+        let mut position = (prompt_width, 0);
+
+        advance(&mut position, left, columns, |_| {});
+        let cursor = wrap(position, columns);
+
+        advance(&mut position, right, columns, |_| {});
+
+        Self { cursor, end: position }
+    }
+
+    /// Returns the number of rows the input takes up.
+    #[inline]
+    pub fn rows(&self) -> u16 {
+        self.cursor.1.max(self.end.1) + 1
+    }
+}
+
+/// Bytes of the input that are written with an attribute, like the selection in
+/// reverse video.
+pub struct Highlight {
+    pub range: Range<usize>,
+    pub attribute: Attribute,
+}
+
+/// Returns the text to write for the input, with [CONTINUATION_PROMPT]s after line
+/// breaks and tabs expanded to spaces, so that it ends up where [Layout::new] puts it.
+/// Highlights may overlap, their attributes are combined.
+pub fn render(input: &str, highlights: &[Highlight], prompt_width: u16, columns: u16) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut position = (prompt_width, 0);
+
+    let mut boundaries = highlights
+        .iter()
+        .flat_map(|highlight| [highlight.range.start, highlight.range.end])
+        .chain([0, input.len()])
+        .collect::<Vec<_>>();
+
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    for part in boundaries.windows(2) {
+        let mut highlighted = false;
+
+        for highlight in highlights.iter().filter(|highlight| highlight.range.start <= part[0] && part[1] <= highlight.range.end) {
+            out.push_str(&SetAttribute(highlight.attribute).to_string());
+            highlighted = true;
+        }
+
+        advance(&mut position, &input[part[0]..part[1]], columns, |text| out.push_str(text));
+
+        if highlighted {
+            out.push_str(&SetAttribute(Attribute::Reset).to_string());
+        }
+    }
+
+    out
+}
+
+/// Moves the position past the text and calls `write` with what to write for each part.
+fn advance(position: &mut (u16, u16), text: &str, columns: u16, mut write: impl FnMut(&str)) {
+    for grapheme in text.graphemes(true) {
+        match grapheme {
+            "\n" | "\r\n" => {
+                *position = (CONTINUATION_PROMPT.len() as u16, position.1 + 1);
+
+                write("\r\n");
+                write(CONTINUATION_PROMPT);
+            }
+            "\t" => {
+                *position = wrap(*position, columns);
+
+                // Tabs don't continue in the next row.
+                let width = (TAB_WIDTH - position.0 % TAB_WIDTH).min(columns - position.0);
+                position.0 += width;
+
+                write(&" ".repeat(width as usize));
+            }
+            _ => {
+                let width = grapheme.width() as u16;
+
+                // Like the terminal, wrap characters that don't fit into the rest of the row.
+                if position.0 + width > columns {
+                    *position = (0, position.1 + 1);
+                }
+
+                position.0 += width;
+
+                write(grapheme);
+            }
+        }
+    }
+}
+
+/// Moves a position past the last column to the start of the next row.
+#[inline]
+fn wrap((column, row): (u16, u16), columns: u16) -> (u16, u16) {
+    if column >= columns {
+        (0, row + 1)
+    } else {
+        (column, row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_layout() {
+        assert_eq!(Layout::new("ls", " -l", 2, 80), Layout { cursor: (4, 0), end: (7, 0) });
+        assert_eq!(Layout::new("ls |\nlen", "gth", 2, 80), Layout { cursor: (7, 1), end: (10, 1) });
+
+        // Lines wrap at the last column, and a full row puts the cursor on the next one.
+        let layout = Layout::new("abcdefgh", "", 2, 5);
+        assert_eq!(layout, Layout { cursor: (0, 2), end: (5, 1) });
+        assert_eq!(layout.rows(), 3);
+
+        assert_eq!(Layout::new("abc", "defghij", 2, 5), Layout { cursor: (0, 1), end: (2, 2) });
+
+        // Wide characters that don't fit move to the next row, tabs go up to the next stop.
+        assert_eq!(Layout::new("日本語", "", 2, 7), Layout { cursor: (2, 1), end: (2, 1) });
+        assert_eq!(Layout::new("e\u{301}\tx", "", 2, 80), Layout { cursor: (9, 0), end: (9, 0) });
+        assert_eq!(render("a\tb\nc", &[], 2, 80), "a     b\r\n... c");
+
+        let highlights = [
+            Highlight { range: 1..3, attribute: Attribute::Reverse },
+            Highlight { range: 2..4, attribute: Attribute::Underlined },
+        ];
+
+        assert_eq!(render("ls -l", &highlights, 2, 80), "l\x1b[7ms\x1b[0m\x1b[7m\x1b[4m \x1b[0m\x1b[4m-\x1b[0ml");
+    }
+}
\ No newline at end of file
@@ -4,34 +4,89 @@ mod text_box;
 mod read;
 mod runtime;
 mod prompt;
-
+mod preview;
+mod units;
+mod diagnostics;
+mod layout;
+mod kill_ring;
+mod vi;
+mod undo;
+mod selection;
+mod clipboard;
+mod keymap;
+mod config;
+mod history;
+mod brackets;
+mod git;
+mod assistant;
+mod completion;
+
+use crate::assistant::{Failure, Model, Progress, Proposal, REQUEST_PREFIX};
+use crate::brackets::{insert_paired, matching};
+use crate::clipboard::CopyToClipboard;
+use crate::completion::complete;
+use crate::config::Config;
+use crate::history::{next_word, Entry, History};
+use crate::keymap::Action;
 use crate::read::bytes::Cursor;
 use crate::read::lex::Lexer;
-use crate::prompt::print_prompt;
+use crate::kill_ring::KillRing;
+use crate::layout::{render, Highlight, Layout};
+use crate::prompt::{print_prompt, Prompt};
+use crate::preview::preview;
+use crate::selection::Selection;
 use crate::text_box::TextBox;
-use crossterm::cursor::{position, MoveTo, MoveToColumn};
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
-use crossterm::style::{Print, Stylize};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crate::undo::{Snapshot, UndoStack};
+use crate::vi::{Mode, Vi};
+use crossterm::cursor::{position, MoveTo, SetCursorStyle};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste, Event, KeyEvent, KeyEventKind};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType};
 use crossterm::{event, execute, queue};
 use std::io::stdout;
+use unicode_width::UnicodeWidthStr;
 use crate::read::buffered::Buffered;
-use crate::read::parse::parse;
-use crate::runtime::{eval, new_root_scope, Value};
+use crate::read::parse::{is_incomplete, parse_recovering};
+use crate::runtime::{change_setting, eval, lookup, new_root_scope, set_variable, Value};
+use crate::units::Quantity;
+use std::time::{Duration, Instant};
+
+/// How often slow prompt segments and proposed commands are checked while waiting for keys.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 fn main() -> ! {
+    let (config, mut warnings) = Config::load();
+    let mut root_scope = new_root_scope();
+
+    for (name, value) in &config.settings {
+        if !change_setting(&mut root_scope, name, value.clone()) {
+            warnings.push(format!("Invalid setting: `{}` = {}", name, value));
+        }
+    }
+
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+
     enable_raw_mode().expect("Failed to enable raw mode");
 
-    let mut root_scope = new_root_scope();
+    // Pasted text arrives as one event, so its line breaks don't submit the input.
+    execute!(stdout(), EnableBracketedPaste).expect("Failed to enable bracketed paste");
 
-    let mut history = Vec::<(String, usize)>::new();
+    let mut history = History::new();
     let mut history_entry_index: Option<usize>;
     let mut text_box = TextBox::new();
+    let mut kill_ring = KillRing::new();
+    let mut vi = Vi::new();
+    let mut undo_stack = UndoStack::new();
+    let mut selection = Selection::new();
 
     let syntax_highlighting = false;
     
     loop {
         text_box.clear();
+        undo_stack.clear();
+        selection.clear();
 
         let mut stdout = stdout();
 
@@ -39,45 +94,183 @@ fn main() -> ! {
 
         // queue!(stdout, MoveToColumn(0)).expect("Failed to move cursor to column");
 
-        print_prompt();
+        // Settings can change with `set`, they apply from the next input.
+        let setting = |name| root_scope.get(name).and_then(|variable| variable.value.get_string());
+        let vi_mode = setting("edit-mode") == Some("vi");
+        let auto_pairs = setting("auto-pairs") == Some("on");
+        vi.reset();
+
+        let indicator = |vi: &Vi| if vi_mode { vi.mode.indicator() } else { "" };
+
+        // Suggest the rest of an earlier input while the cursor is at the end.
+        let directory = std::env::current_dir().ok();
+
+        let suggest = |text_box: &TextBox| match text_box.parts() {
+            (left, "") => history.suggest(left, directory.as_deref()),
+            _ => None,
+        };
+
+        let mut prompt = Prompt::render(&root_scope, "prompt", true);
+        let mut right_prompt = Prompt::render(&root_scope, "right-prompt", true);
+        let mut right_width = right_prompt.width() as u16;
 
-        let (min_cursor_position, y) = position()
+        print_prompt(indicator(&vi), &prompt);
+
+        let (mut min_cursor_position, mut y) = position()
             .expect("Failed to get cursor position");
 
+        let (columns, _) = size().expect("Failed to get terminal size");
+
+        // The prompt can be drawn again in place if it doesn't wrap.
+        let prompt_fits = |min_cursor_position: u16, prompt: &Prompt, vi: &Vi| {
+            indicator(vi).len() + prompt.width() == min_cursor_position as usize
+        };
+
+        if right_width > 0 && min_cursor_position + right_width < columns {
+            execute!(
+                stdout,
+                MoveTo(columns - right_width, y),
+                Print(&right_prompt.text),
+                MoveTo(min_cursor_position, y)
+            ).expect("Failed to print right prompt");
+        }
+
+        // Whether the last key killed text, and how many chars the last key yanked.
+        let mut last_kill = false;
+        let mut last_yank = None;
+
+        // The command that the model proposes for a request, and an error shown after the
+        // input until the next key.
+        let mut proposal: Option<Proposal> = None;
+        let mut notice: Option<String> = None;
+
+        // The input that is searched for in the history, and the index of the entry found.
+        let mut search: Option<(String, usize)> = None;
+
         loop {
-            match event::read().expect("Failed to read an event") {
-                Event::FocusGained => {}
-                Event::FocusLost => {}
-                Event::Key(KeyEvent { code: key, kind, .. }) => {
+            // While slow prompt segments are computed or a command streams in, keys are
+            // waited for briefly, to draw the prompt or the command as they change.
+            let event = if (prompt.pending || right_prompt.pending || proposal.is_some())
+                && !event::poll(POLL_INTERVAL).expect("Failed to poll events")
+            {
+                let mut changed = false;
+
+                if let Some(current) = &mut proposal {
+                    let progress = current.poll();
+                    text_box.clear();
+
+                    if let Progress::Failed(error) = progress {
+                        text_box.insert_str(&current.input);
+                        notice = Some(error);
+                        proposal = None;
+                    } else {
+                        text_box.insert_str(&current.command());
+
+                        if let Progress::Done = progress {
+                            proposal = None;
+                        }
+                    }
+
+                    changed = true;
+                }
+
+                if prompt.pending || right_prompt.pending {
+                    let updated = Prompt::render(&root_scope, "prompt", false);
+                    let updated_right = Prompt::render(&root_scope, "right-prompt", false);
+
+                    if !(updated.pending || updated_right.pending) {
+                        let (columns, _) = size().expect("Failed to get terminal size");
+
+                        // The input is drawn again below, after the new prompt.
+                        if prompt_fits(min_cursor_position, &prompt, &vi) && indicator(&vi).len() + updated.width() < columns as usize {
+                            queue!(stdout, MoveTo(0, y), Clear(ClearType::FromCursorDown), Print(indicator(&vi)), Print(&updated.text))
+                                .expect("Failed to queue prompt");
+
+                            min_cursor_position = (indicator(&vi).len() + updated.width()) as u16;
+                        }
+
+                        prompt = updated;
+                        right_prompt = updated_right;
+                        right_width = right_prompt.width() as u16;
+                        changed = true;
+                    }
+                }
+
+                if !changed {
+                    continue;
+                }
+
+                None
+            } else {
+                Some(event::read().expect("Failed to read an event"))
+            };
+
+            match event {
+                None => {}
+                Some(Event::FocusGained) => {}
+                Some(Event::FocusLost) => {}
+                Some(Event::Key(KeyEvent { code: key, modifiers, kind, .. })) => {
                     if kind == KeyEventKind::Release || kind == KeyEventKind::Repeat {
                         continue;
                     }
 
-                    match key {
-                        KeyCode::Backspace => text_box.remove_char_left(),
-                        KeyCode::Enter => break,
-                        KeyCode::Left => {
-                            text_box.move_cursor_n_chars_left(1);
+                    // A key stops the command that streams in, and keeps what arrived.
+                    if proposal.take().is_some() {
+                        continue;
+                    }
+
+                    notice = None;
+
+                    let before = Snapshot::of(&text_box);
+
+                    // In vi mode, keys that vi doesn't handle work like in emacs mode.
+                    let action = if vi_mode {
+                        vi.handle_key(&mut text_box, &config.keymap, key, modifiers)
+                    } else {
+                        config.keymap.action(key, modifiers)
+                    };
+
+                    let mut killed = None;
+                    let mut yanked = None;
+                    let mut selecting = false;
+                    let mut searching = false;
+
+                    // Typing replaces the selection, and deleting removes only the selection.
+                    let replaced = matches!(action, Some(Action::Insert(_) | Action::DeleteLeft | Action::DeleteRight))
+                        && selection.take(&mut text_box, true).is_some();
+
+                    let suggestion = suggest(&text_box);
+
+                    match action {
+                        None => {}
+                        Some(Action::DeleteLeft | Action::DeleteRight) if replaced => {}
+                        Some(Action::MoveRight | Action::MoveToLineEnd) if suggestion.is_some() => {
+                            text_box.insert_str(suggestion.unwrap_or_default());
                         }
-                        KeyCode::Right => {
-                            text_box.move_cursor_n_chars_right(1);
+                        Some(Action::MoveWordRight) if suggestion.is_some() => {
+                            text_box.insert_str(next_word(suggestion.unwrap_or_default()));
                         }
-                        KeyCode::Up => {
+                        Some(Action::Insert(c)) if auto_pairs => insert_paired(&mut text_box, c),
+                        Some(Action::Insert(c)) => text_box.insert_char(c),
+                        Some(Action::MoveLeft) => text_box.move_cursor_left(),
+                        Some(Action::MoveRight) => text_box.move_cursor_right(),
+                        // Move between the lines of the input first, then through the history.
+                        Some(Action::MoveUp) if !text_box.move_cursor_up() && !history.entries().is_empty() => {
                             let new_history_entry_index = history_entry_index
-                                .unwrap_or_else(|| history.len())
+                                .unwrap_or_else(|| history.entries().len())
                                 .saturating_sub(1);
 
                             // Update text box with new history entry
 
                             text_box.clear();
 
-                            let (str, char_count) = &history[new_history_entry_index];
-                            unsafe { text_box.insert_str_with_cached_char_count(str, *char_count); }
+                            let entry = &history.entries()[new_history_entry_index];
+                            unsafe { text_box.insert_str_with_cached_char_count(&entry.text, entry.char_count); }
 
                             history_entry_index = Some(new_history_entry_index);
                         }
-                        KeyCode::Down => {
-                            let max = history.len() - 1;
+                        Some(Action::MoveDown) if !text_box.move_cursor_down() && !history.entries().is_empty() => {
+                            let max = history.entries().len() - 1;
 
                             let new_history_entry_index = (history_entry_index
                                 .unwrap_or_else(|| max) + 1).min(max);
@@ -86,77 +279,412 @@ fn main() -> ! {
 
                             text_box.clear();
 
-                            let (str, char_count) = &history[new_history_entry_index];
-                            unsafe { text_box.insert_str_with_cached_char_count(str, *char_count); }
+                            let entry = &history.entries()[new_history_entry_index];
+                            unsafe { text_box.insert_str_with_cached_char_count(&entry.text, entry.char_count); }
 
                             history_entry_index = Some(new_history_entry_index);
                         }
-                        KeyCode::Char(c) => {
-                            text_box.insert_char(c);
+                        Some(Action::MoveUp | Action::MoveDown) => {}
+                        Some(Action::MoveWordLeft) => text_box.move_cursor_word_left(),
+                        Some(Action::MoveWordRight) => text_box.move_cursor_word_right(),
+                        Some(Action::MoveToLineStart) => text_box.move_cursor_to_line_start(),
+                        Some(Action::MoveToLineEnd) => text_box.move_cursor_to_line_end(),
+                        Some(
+                            action @ (Action::SelectLeft
+                            | Action::SelectRight
+                            | Action::SelectUp
+                            | Action::SelectDown
+                            | Action::SelectToLineStart
+                            | Action::SelectToLineEnd)
+                        ) => {
+                            selection.extend(&text_box);
+                            selecting = true;
+
+                            match action {
+                                Action::SelectLeft => text_box.move_cursor_left(),
+                                Action::SelectRight => text_box.move_cursor_right(),
+                                Action::SelectUp => _ = text_box.move_cursor_up(),
+                                Action::SelectDown => _ = text_box.move_cursor_down(),
+                                Action::SelectToLineStart => text_box.move_cursor_to_line_start(),
+                                _ => text_box.move_cursor_to_line_end(),
+                            }
+                        }
+                        Some(Action::DeleteLeft) => text_box.remove_grapheme_left(),
+                        Some(Action::DeleteRight) => text_box.remove_grapheme_right(),
+                        // Copy the selection to the kill ring and the system clipboard.
+                        Some(action @ (Action::Copy | Action::Cut)) if selection.range(&text_box).is_some() => {
+                            if let Some(text) = selection.take(&mut text_box, action == Action::Cut) {
+                                execute!(stdout, CopyToClipboard(&text)).expect("Failed to copy to the clipboard");
+                                kill_ring.kill(text, false, false);
+                            }
+                        }
+                        Some(Action::Copy) => {}
+                        Some(Action::Cut | Action::KillWordLeft) => killed = Some((text_box.kill_word_left(), true)),
+                        Some(Action::KillWordRight) => killed = Some((text_box.kill_word_right(), false)),
+                        Some(Action::KillToLineStart) => killed = Some((text_box.kill_to_line_start(), true)),
+                        Some(Action::KillToLineEnd) => killed = Some((text_box.kill_to_line_end(), false)),
+                        Some(Action::Yank) => {
+                            if let Some(text) = kill_ring.yank() {
+                                text_box.insert_str(text);
+                                yanked = Some(text.chars().count());
+                            }
+                        }
+                        // Replace the text that was just yanked with the entry before it.
+                        Some(Action::YankPop) if last_yank.is_some() => {
+                            if let Some(text) = kill_ring.yank_pop() {
+                                text_box.remove_n_chars_left(last_yank.unwrap_or(0));
+                                text_box.insert_str(text);
+                                yanked = Some(text.chars().count());
+                            }
+                        }
+                        Some(Action::YankPop) => {}
+                        Some(Action::Transpose) => text_box.transpose_graphemes(),
+                        Some(Action::Undo) => undo_stack.undo(&mut text_box),
+                        Some(Action::Redo) => undo_stack.redo(&mut text_box),
+                        Some(Action::ClearScreen) => {
+                            execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))
+                                .expect("Failed to clear the screen");
+
+                            print_prompt(indicator(&vi), &prompt);
+                            y = 0;
+                        }
+                        Some(Action::InsertNewline) => text_box.insert_char('\n'),
+                        Some(Action::Complete) => {
+                            if let Some(completion) = directory.as_deref().and_then(|directory| complete(text_box.parts().0, directory)) {
+                                text_box.insert_str(&completion);
+                            }
+                        }
+                        // Search from the entry found last while the key is pressed again.
+                        Some(Action::HistorySearch) => {
+                            let (query, before) = search.take().unwrap_or_else(|| {
+                                let (left, right) = text_box.parts();
+                                (format!("{}{}", left, right), history.entries().len())
+                            });
+
+                            let found = history.search(&query, before);
+
+                            if let Some(index) = found {
+                                text_box.clear();
+
+                                let entry = &history.entries()[index];
+                                unsafe { text_box.insert_str_with_cached_char_count(&entry.text, entry.char_count); }
+
+                                history_entry_index = Some(index);
+                            }
+
+                            search = Some((query, found.unwrap_or(before)));
+                            searching = true;
+                        }
+                        Some(Action::AcceptLine) => {
+                            let (left, right) = text_box.parts();
+                            let input = format!("{}{}", left, right);
+
+                            let request = input.strip_prefix(REQUEST_PREFIX)
+                                .map(str::trim)
+                                .filter(|request| !request.is_empty());
+
+                            // Requests aren't run, the proposed command replaces them instead.
+                            if let Some(request) = request {
+                                let recent = history.entries().iter().map(|entry| entry.text.as_str()).collect::<Vec<_>>();
+                                let directory = directory.as_deref().map(|directory| directory.display().to_string());
+
+                                proposal = Some(Proposal::start(
+                                    &Model::from_scope(&root_scope),
+                                    &input,
+                                    request,
+                                    directory.as_deref().unwrap_or_default(),
+                                    &recent
+                                ));
+
+                                text_box.clear();
+                            } else if !is_incomplete(&input) {
+                                // Continue in a new line until the input can be complete.
+                                break;
+                            } else {
+                                text_box.insert_char('\n');
+                            }
                         }
-                        _ => {}
                     };
+
+                    if !matches!(action, Some(Action::Undo | Action::Redo)) {
+                        undo_stack.record(before, &text_box, matches!(action, Some(Action::Insert(_))));
+                    }
+
+                    if !selecting {
+                        selection.clear();
+                    }
+
+                    if !searching {
+                        search = None;
+                    }
+
+                    let continues_kill = last_kill;
+                    last_kill = killed.is_some();
+                    last_yank = yanked;
+
+                    if let Some((text, backward)) = killed {
+                        kill_ring.kill(text, continues_kill, backward);
+                    }
+                }
+                Some(Event::Mouse(_)) => {}
+                Some(Event::Paste(text)) => {
+                    proposal = None;
+
+                    let before = Snapshot::of(&text_box);
+                    selection.take(&mut text_box, true);
+
+                    // Keep line breaks for multiline editing, terminals send them as `\r`.
+                    text_box.insert_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+                    undo_stack.record(before, &text_box, false);
+                }
+                Some(Event::Resize(columns, _)) => {
+                    // The terminal reflows the input, find the row of the prompt again.
+                    let (left, right) = text_box.parts();
+                    let layout = Layout::new(left, right, min_cursor_position, columns);
+
+                    if let Ok((_, cursor_y)) = position() {
+                        y = cursor_y.saturating_sub(layout.cursor.1);
+                    }
                 }
-                Event::Mouse(_) => {}
-                Event::Paste(_) => {}
-                Event::Resize(_, _) => {}
             }
 
+            let (columns, rows) = size().expect("Failed to get terminal size");
+
             queue!(
                 stdout,
-                MoveTo(min_cursor_position, y)
+                MoveTo(min_cursor_position, y),
+                Clear(ClearType::FromCursorDown)
             ).expect("Failed to queue cursor movement");
 
+            if vi_mode {
+                // The mode can change with every key, the indicator has the same width.
+                let style = if vi.mode == Mode::Insert { SetCursorStyle::SteadyBar } else { SetCursorStyle::SteadyBlock };
+
+                queue!(stdout, MoveTo(0, y), Print(indicator(&vi)), style)
+                    .expect("Failed to queue mode indicator");
+            }
+
             let partition = text_box.parts();
+            let suggestion = suggest(&text_box);
+
+            // The layout includes the suggestion, which is dimmed after the input.
+            let layout = Layout::new(
+                partition.0,
+                &format!("{}{}", partition.1, suggestion.unwrap_or_default()),
+                min_cursor_position,
+                columns
+            );
+
+            let input = format!("{}{}", partition.0, partition.1);
+
+            let mut highlights = Vec::new();
+
+            if let Some(range) = vi.selection(&text_box).or(selection.bytes(&text_box)) {
+                highlights.push(Highlight { range, attribute: Attribute::Reverse });
+            }
+
+            // Underline the bracket or quote at the cursor and its partner.
+            if let Some((bracket, partner)) = matching(&text_box) {
+                highlights.push(Highlight { range: bracket..bracket + 1, attribute: Attribute::Underlined });
+                highlights.push(Highlight { range: partner..partner + 1, attribute: Attribute::Underlined });
+            }
 
             if syntax_highlighting {
 
             } else {
-                queue!(stdout, Print(partition.0), Print(partition.1))
-                    .expect("Failed to queue partition");
+                queue!(stdout, Print(render(&input, &highlights, min_cursor_position, columns)))
+                    .expect("Failed to queue input");
+            }
+
+            if let Some(suggestion) = suggestion {
+                let input_end = Layout::new(&input, "", min_cursor_position, columns).end;
+
+                queue!(
+                    stdout,
+                    SetAttribute(Attribute::Dim),
+                    Print(render(suggestion, &[], input_end.0, columns)),
+                    SetAttribute(Attribute::Reset)
+                ).expect("Failed to queue suggestion");
+            }
+
+            if layout.end.0 >= columns {
+                // The last row is full, start the next one for the cursor.
+                queue!(stdout, Print("\r\n")).expect("Failed to queue new line");
+            } else if let Some(notice) = &notice {
+                let width = (columns - layout.end.0) as usize;
+
+                if width > 3 {
+                    queue!(
+                        stdout,
+                        Print("  "),
+                        SetForegroundColor(Color::Red),
+                        Print(notice.chars().take(width - 3).collect::<String>()),
+                        SetForegroundColor(Color::Reset)
+                    ).expect("Failed to queue notice");
+                }
+            } else if suggestion.is_some() {
+                // The preview would be mistaken for part of the suggestion.
+            } else if let Some(value) = preview(&mut root_scope, &input) {
+                // Show the result of pure expressions dimmed after the input, if it fits.
+                let width = layout.end.0 as usize + value.to_argument().width() + right_width as usize + 5;
+
+                if width < columns as usize {
+                    queue!(
+                        stdout,
+                        SetAttribute(Attribute::Dim),
+                        Print(format!("  = {}", value)),
+                        SetAttribute(Attribute::Reset)
+                    ).expect("Failed to queue preview");
+                }
             }
 
+            // The right prompt is hidden once the input reaches it.
+            if layout.rows() == 1 && right_width > 0 && layout.end.0 + right_width < columns {
+                queue!(stdout, MoveTo(columns - right_width, y), Print(&right_prompt.text))
+                    .expect("Failed to queue right prompt");
+            }
+
+            // The terminal scrolls if the input reaches past the last row.
+            y = y.min(rows.saturating_sub(layout.rows()));
+
             execute!(
                 stdout,
-                Print(" "),
-                MoveTo(text_box.chars_left_from_cursor() as u16 + min_cursor_position, y)
+                MoveTo(layout.cursor.0, y + layout.cursor.1)
             ).expect("Failed to print input");
         }
 
-        execute!(stdout, Print("\n\r")).unwrap();
-
         text_box.move_cursor_to_end();
 
+        if vi_mode {
+            execute!(stdout, SetCursorStyle::DefaultUserShape).expect("Failed to reset cursor style");
+        }
+
         let (input, _) = text_box.parts();
-        history.push((input.to_string(), text_box.chars_left_from_cursor()));
+        let (columns, rows) = size().expect("Failed to get terminal size");
+        let transient_prompt = Prompt::render(&root_scope, "transient-prompt", false);
+
+        // Replace the prompt with the compact one in the scrollback, and draw the input
+        // after it again. This also removes the preview and the suggestion.
+        if !transient_prompt.text.is_empty()
+            && prompt_fits(min_cursor_position, &prompt, &vi)
+            && transient_prompt.width() < columns as usize
+        {
+            min_cursor_position = transient_prompt.width() as u16;
+
+            queue!(
+                stdout,
+                MoveTo(0, y),
+                Clear(ClearType::FromCursorDown),
+                Print(&transient_prompt.text),
+                Print(render(input, &[], min_cursor_position, columns))
+            ).expect("Failed to queue transient prompt");
+
+            y = y.min(rows.saturating_sub(Layout::new(input, "", min_cursor_position, columns).rows()));
+        }
+
+        // Remove the preview and the suggestion, the result is printed below.
+        let end = Layout::new(input, "", min_cursor_position, columns).cursor;
+
+        execute!(
+            stdout,
+            MoveTo(end.0, y + end.1),
+            Clear(ClearType::FromCursorDown),
+            Print("\n\r")
+        ).unwrap();
+
+        history.push(Entry {
+            text: input.to_string(),
+            char_count: text_box.chars_left_from_cursor(),
+            directory,
+            succeeded: true,
+        });
 
         if input.is_empty() {
             continue;
         }
 
         let mut iter = Buffered::new(Lexer::new(Cursor::new(input)));
-        let root_expression = if let Ok(expr) = parse(&mut iter, 0) {
-            expr
-        } else {
-            execute!(stdout, Print("Error: Failed to parse input\n\r"));
+        let (root_expression, errors) = parse_recovering(&mut iter);
+
+        if !errors.is_empty() {
+            let messages = errors.iter().map(|error| error.value.to_string()).collect::<Vec<_>>();
+
+            for error in errors {
+                queue!(
+                    stdout,
+                    Print(error.value.diagnostic(error.range).render(input).replace('\n', "\n\r"))
+                ).expect("Failed to queue error");
+            }
+
+            execute!(stdout).expect("Failed to print errors");
+
+            set_variable(&mut root_scope, "status", Value::Int(1));
+            set_variable(&mut root_scope, "duration", Value::Quantity(Quantity::duration(0.0)));
+            history.mark_failed();
+
+            Failure {
+                input: input.to_string(),
+                status: 1,
+                errors: messages.join("\n"),
+            }.remember(&mut root_scope);
+
             continue;
-        };
+        }
 
         let _ = stdout;
 
+        execute!(stdout, DisableBracketedPaste).expect("Failed to disable bracketed paste");
         disable_raw_mode().expect("Failed to disable raw mode");
+
+        // External programs set the status, other inputs succeed unless they fail.
+        set_variable(&mut root_scope, "status", Value::Int(0));
+        set_variable(&mut root_scope, "stderr-tail", Value::String(String::new()));
+
+        let start = Instant::now();
         let result = eval(&mut root_scope, &root_expression);
+        let duration = Quantity::duration(start.elapsed().as_secs_f64());
+
+        set_variable(&mut root_scope, "duration", Value::Quantity(duration));
+
+        let mut error_message = None;
 
         match result {
             Ok(Value::Nil) => {}
+            Ok(Value::Table(table)) => {
+                print!("{}", table);
+            }
+            Ok(value @ (Value::List(_) | Value::Record(_))) => {
+                println!("{}", value);
+            }
             Ok(value) => {
                 println!("= {}", value);
             }
             Err(runtime_error) => {
-                println!("Error: {:?}", runtime_error);
+                print!("{}", runtime_error.diagnostic().render(input));
+                set_variable(&mut root_scope, "status", Value::Int(1));
+                error_message = Some(runtime_error.to_string());
+            }
+        }
+
+        match lookup(&root_scope, "status") {
+            Some(Value::Int(0)) => {}
+            status => {
+                let status = match status {
+                    Some(Value::Int(status)) => *status,
+                    _ => 1,
+                };
+
+                // The error of the shell, or else what the programs printed to stderr.
+                let errors = error_message.unwrap_or_else(|| {
+                    lookup(&root_scope, "stderr-tail").and_then(Value::get_string).unwrap_or_default().to_string()
+                });
+
+                history.mark_failed();
+                Failure { input: input.to_string(), status, errors }.remember(&mut root_scope);
             }
         }
 
         enable_raw_mode().expect("Failed to enable raw mode");
+        execute!(stdout, EnableBracketedPaste).expect("Failed to enable bracketed paste");
     }
 }
\ No newline at end of file
@@ -0,0 +1,54 @@
+//! # Live preview of pure expressions while typing.
+
+use crate::read::ast::Expression;
+use crate::read::buffered::Buffered;
+use crate::read::bytes::Cursor;
+use crate::read::lex::Lexer;
+use crate::read::parse::parse;
+use crate::runtime::{eval, is_pure_command, Scope, Value};
+
+/// Evaluates the input if it is a pure expression and returns the result if it fits
+/// on a single line. Nothing is returned for invalid or impure input, for errors, for
+/// structured results like tables, and for literals, which are their own result.
+pub fn preview(scope: &mut Scope, input: &str) -> Option<Value> {
+    let mut iter = Buffered::new(Lexer::new(Cursor::new(input)));
+    let expression = parse(&mut iter).ok()?;
+
+    let literal = matches!(
+        expression.value,
+        Expression::Number(_) | Expression::Integer(_) | Expression::Quantity(..) | Expression::String(_)
+    );
+
+    if literal {
+        return None;
+    }
+
+    if !expression.value.is_pure(&|name, arguments| is_pure_command(scope, name, arguments)) {
+        return None;
+    }
+
+    match eval(scope, &expression).ok()? {
+        value @ (Value::Number(_) | Value::Int(_) | Value::Quantity(_) | Value::Bool(_)) => Some(value),
+        Value::String(s) if !s.contains('\n') => Some(Value::String(s)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::new_root_scope;
+
+    #[test]
+    pub fn test_preview() {
+        let mut scope = new_root_scope();
+
+        assert_eq!(preview(&mut scope, "1 + 2"), Some(Value::Int(3)));
+        assert_eq!(preview(&mut scope, "math.sqrt(16) + math.pi * 0"), Some(Value::Number(4.0)));
+        assert_eq!(preview(&mut scope, "hex(255)"), Some(Value::String("0xff".to_string())));
+        assert_eq!(preview(&mut scope, "42"), None);
+        assert_eq!(preview(&mut scope, "1 +"), None);
+        assert_eq!(preview(&mut scope, "ls | length"), None);
+        assert_eq!(preview(&mut scope, "rm -rf target"), None);
+    }
+}
\ No newline at end of file
@@ -1,14 +1,211 @@
+//! # The prompt, rendered from the templates in the `prompt` and `right-prompt` settings.
+//!
+//! Templates are text with segments like `{cwd}{status}> `. The segments are:
+//!
+//! * `{cwd}`: the working directory, with the home directory as `~`.
+//! * `{status}`: the exit status of the last input in red, if it failed.
+//! * `{duration}`: how long the last input ran, if it took at least two seconds.
+//! * `{user}` and `{host}`: the name of the user and of the computer.
+//! * `{utc-time}`: the time as `HH:MM:SS` in UTC, like the times of `ls`. The shell doesn't
+//!   know the local time zone, so there is no segment for the local time.
+//! * `{git}`: the branch and status of the git repository, like `main ↑1 +!?`.
+//!
+//! Any other segment is replaced by the variable of that name, like `{HOME}`.
+//!
+//! Slow segments, like `{git}`, are computed in the background. Until then they show a
+//! dimmed placeholder, and the prompt is drawn again once they are done.
+//!
+//! After an input is run, its prompt is replaced by the `transient-prompt`, to keep the
+//! scrollback compact. An empty `transient-prompt` keeps the full prompt.
+
+use crate::git;
+use crate::runtime::{lookup, Scope, Value};
+use crossterm::style::{Attribute, Color, SetAttribute, SetForegroundColor};
 use std::io::{stdout, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_width::UnicodeWidthStr;
+
+/// The prompt in front of the lines after the first one of a multiline input.
+pub const CONTINUATION_PROMPT: &str = "... ";
+
+/// The shortest duration of an input in seconds that the `{duration}` segment shows.
+const DURATION_THRESHOLD: f64 = 2.0;
 
-pub fn print_prompt() {
-    let cwd = std::env::current_dir();
+/// A prompt rendered from a template.
+pub struct Prompt {
+    pub text: String,
+    /// Whether slow segments show placeholders, until they are computed.
+    pub pending: bool,
+}
+
+impl Prompt {
+    /// Renders the template in the setting, like `prompt`. With `refresh`, slow segments
+    /// are computed again, otherwise they show what was computed last.
+    #[inline]
+    pub fn render(scope: &Scope, setting: &str, refresh: bool) -> Self {
+        let template = lookup(scope, setting).and_then(Value::get_string).unwrap_or_default();
+        render_template(scope, template, refresh)
+    }
 
-    if let Ok(cwd) = cwd {
-        print!("{}> ", cwd.display());
-    } else {
-        print!("?> ");
+    #[inline]
+    pub fn width(&self) -> usize {
+        visible_width(&self.text)
     }
+}
+
+/// Prints the prompt after the `indicator`, which shows the mode of the line editor.
+pub fn print_prompt(indicator: &str, prompt: &Prompt) {
+    print!("{}{}", indicator, prompt.text);
 
     stdout().flush()
         .expect("Failed to flush stdout");
+}
+
+/// Replaces the segments in the template. Braces that don't form a segment are kept.
+pub fn render_template(scope: &Scope, template: &str, refresh: bool) -> Prompt {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    let mut pending = false;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        match segment(scope, &rest[1..end], refresh, &mut pending) {
+            Some(text) => out.push_str(&text),
+            None => out.push_str(&rest[..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    out.push_str(rest);
+    Prompt { text: out, pending }
+}
+
+fn segment(scope: &Scope, name: &str, refresh: bool, pending: &mut bool) -> Option<String> {
+    let text = match name {
+        "cwd" => match std::env::current_dir() {
+            Ok(cwd) => {
+                let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+                shorten(&cwd, home.as_deref().map(Path::new))
+            }
+            Err(_) => "?".to_string(),
+        },
+        "status" => match lookup(scope, "status") {
+            Some(Value::Int(status)) if *status != 0 => colored(Color::Red, &format!("[{}]", status)),
+            _ => String::new(),
+        },
+        "duration" => match lookup(scope, "duration") {
+            Some(Value::Quantity(duration)) if duration.base_amount() >= DURATION_THRESHOLD => {
+                colored(Color::Yellow, &format!("took {}", duration))
+            }
+            _ => String::new(),
+        },
+        "git" => match std::env::current_dir().ok().and_then(|cwd| git::cached_status(&cwd, refresh)) {
+            // The last status is the placeholder while the status is read again.
+            Some((Some(status), true)) => {
+                *pending = true;
+                dimmed(&colored(Color::Magenta, &status.to_string()))
+            }
+            Some((None, true)) => {
+                *pending = true;
+                dimmed("…")
+            }
+            Some((Some(status), false)) => colored(Color::Magenta, &status.to_string()),
+            Some((None, false)) | None => String::new(),
+        },
+        "user" => std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default(),
+        "host" => hostname(),
+        "utc-time" => {
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+
+            format!("{:02}:{:02}:{:02}", seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60)
+        }
+        name => lookup(scope, name).filter(|value| !matches!(value, Value::Function(_)))?.to_argument(),
+    };
+
+    Some(text)
+}
+
+/// Writes the directory with the home directory replaced by `~`.
+fn shorten(directory: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| directory.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        None => directory.display().to_string(),
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname").map(|name| name.trim().to_string()))
+        .unwrap_or_default()
+}
+
+#[inline]
+fn colored(color: Color, text: &str) -> String {
+    format!("{}{}{}", SetForegroundColor(color), text, SetForegroundColor(Color::Reset))
+}
+
+#[inline]
+fn dimmed(text: &str) -> String {
+    format!("{}{}{}", SetAttribute(Attribute::Dim), text, SetAttribute(Attribute::NormalIntensity))
+}
+
+/// Returns the number of columns the text takes up, without its escape sequences.
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut rest = text;
+
+    while let Some(start) = rest.find('\x1b') {
+        width += rest[..start].width();
+
+        // Escape sequences for colors end at the first letter.
+        rest = &rest[start + 1..];
+        rest = rest.find(|c: char| c.is_ascii_alphabetic()).map_or("", |end| &rest[end + 1..]);
+    }
+
+    width + rest.width()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Variable;
+    use crate::units::{find_unit, Quantity};
+
+    #[test]
+    pub fn test_render_template() {
+        let mut scope = Scope::new();
+
+        let mut set = |name: &str, value| {
+            scope.insert(name.to_string(), Variable { mutable: false, value });
+        };
+
+        set("status", Value::Int(2));
+        set("duration", Value::Quantity(Quantity::new(1.5, find_unit("s").unwrap())));
+        set("name", Value::String("shesh".to_string()));
+
+        let prompt = render_template(&scope, "{name}{status}{duration} {nothing}> ", false);
+
+        assert_eq!(prompt.text, "shesh\x1b[38;5;9m[2]\x1b[39m {nothing}> ");
+        assert_eq!(prompt.width(), 20);
+        assert!(!prompt.pending);
+
+        let time = render_template(&scope, "{utc-time}", false).text;
+        assert!(time.len() == 8 && time.split(':').all(|part| part.parse::<u8>().is_ok()), "{}", time);
+
+        assert_eq!(shorten(Path::new("/home/me/src"), Some(Path::new("/home/me"))), "~/src");
+        assert_eq!(shorten(Path::new("/home/me"), Some(Path::new("/home/me"))), "~");
+        assert_eq!(shorten(Path::new("/etc"), Some(Path::new("/home/me"))), "/etc");
+    }
 }
\ No newline at end of file
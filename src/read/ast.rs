@@ -1,4 +1,5 @@
 use crate::read::bytes::Span;
+use crate::units::Unit;
 
 #[derive(Debug)]
 pub enum BinaryOperation {
@@ -24,6 +25,12 @@ pub enum BinaryOperation {
     GreaterThanOrEqual,
 }
 
+#[derive(Debug)]
+pub enum UnaryOperation {
+    Negate,
+    Not,
+}
+
 #[derive(Debug)]
 pub enum Expression<'a> {
     Binary {
@@ -31,7 +38,66 @@ pub enum Expression<'a> {
         operation: BinaryOperation,
         right: Box<Span<Expression<'a>>>,
     },
-    CommandInvocation(&'a str),
+    Unary {
+        operation: UnaryOperation,
+        operand: Box<Span<Expression<'a>>>,
+    },
+    /// Stages separated by `|`, each receiving the value of the previous one.
+    Pipeline(Vec<Span<Expression<'a>>>),
+    CommandInvocation {
+        name: &'a str,
+        arguments: Vec<Span<Expression<'a>>>,
+    },
+    /// Converts a quantity into another unit, like `10MiB to kB`.
+    Conversion {
+        operand: Box<Span<Expression<'a>>>,
+        unit: &'static Unit,
+    },
     Number(f64),
+    Integer(i64),
+    Quantity(f64, &'static Unit),
+    /// A number in the arguments of a command, like the `01` in `cat 01`. Builtins get
+    /// its value, external programs the text as it was typed.
+    Numeral {
+        value: Box<Span<Expression<'a>>>,
+        text: &'a str,
+    },
     String(&'a str),
+    /// A bare word in argument position, like `-a` or `src/main.rs`.
+    Word(&'a str),
+    /// A part of the input that couldn't be parsed, see [parse_recovering].
+    ///
+    /// [parse_recovering]: crate::read::parse::parse_recovering
+    Error,
+}
+
+impl Expression<'_> {
+    /// Returns `true` if evaluating the expression has no side effects, so that it can
+    /// be evaluated while it is being typed. Literals and operators are pure, a command
+    /// invocation is pure if `is_pure_command` returns `true` for it and its arguments
+    /// are pure.
+    pub fn is_pure(&self, is_pure_command: &impl Fn(&str, &[Span<Expression>]) -> bool) -> bool {
+        match self {
+            Expression::Binary { left, right, .. } => {
+                left.value.is_pure(is_pure_command) && right.value.is_pure(is_pure_command)
+            }
+            Expression::Unary { operand, .. } | Expression::Conversion { operand, .. } => {
+                operand.value.is_pure(is_pure_command)
+            }
+            Expression::Numeral { value, .. } => value.value.is_pure(is_pure_command),
+            Expression::Pipeline(stages) => stages
+                .iter()
+                .all(|stage| stage.value.is_pure(is_pure_command)),
+            Expression::CommandInvocation { name, arguments } => {
+                is_pure_command(name, arguments)
+                    && arguments.iter().all(|argument| argument.value.is_pure(is_pure_command))
+            }
+            Expression::Number(_)
+                | Expression::Integer(_)
+                | Expression::Quantity(..)
+                | Expression::String(_)
+                | Expression::Word(_) => true,
+            Expression::Error => false,
+        }
+    }
 }
\ No newline at end of file
@@ -10,11 +10,13 @@ pub const BITWISE_AND: (u8, u8) = (10, 11);
 pub const BITWISE_XOR: (u8, u8) = (12, 13);
 pub const EQUALITY: (u8, u8) = (14, 15);
 pub const RELATIONAL: (u8, u8) = (16, 17);
-pub const SHIFT: (u8, u8) = (18, 19);
-pub const ADDITIVE: (u8, u8) = (20, 21);
-pub const MULTIPLICATIVE: (u8, u8) = (22, 23);
-pub const EXPONENTIAL: (u8, u8) = (24, 25);
+pub const CONVERSION: u8 = 18;
+pub const SHIFT: (u8, u8) = (20, 21);
+pub const ADDITIVE: (u8, u8) = (22, 23);
+pub const MULTIPLICATIVE: (u8, u8) = (24, 25);
+/// Below exponentiation, so that `-2 ** 2` is `-(2 ** 2)`.
 pub const NEGATE_AND_NOT: u8 = 26;
-pub const CALL: u8 = 27;
-pub const ACCESS_AND_OPTIONAL_ACCESS: u8 = 28;
-pub const BLOCK: u8 = 29;
\ No newline at end of file
+pub const EXPONENTIAL: (u8, u8) = (28, 27);
+pub const CALL: u8 = 29;
+pub const ACCESS_AND_OPTIONAL_ACCESS: u8 = 30;
+pub const BLOCK: u8 = 31;
\ No newline at end of file
@@ -1,6 +1,8 @@
-use crate::read::bytes::Span;
+use crate::read::bytes::{Index, Span};
+use crate::read::error::ReadError;
 use crate::read::lex::Lexer;
 use crate::read::tokens::Token;
+use std::ops::Range;
 
 pub struct Buffered<'a> {
     lexer: Lexer<'a>,
@@ -17,7 +19,7 @@ impl<'a> Buffered<'a> {
     }
 
     #[inline]
-    pub fn peek(&mut self) -> Result<&Span<Token<'a>>, ()> {
+    pub fn peek(&mut self) -> Result<&Span<Token<'a>>, Span<ReadError>> {
         if self.force_take.is_some() {
             return Ok(unsafe {
                 self.force_take.as_ref().unwrap_unchecked()
@@ -28,10 +30,49 @@ impl<'a> Buffered<'a> {
     }
 
     #[inline]
-    pub fn next(&mut self) -> Result<Span<Token<'a>>, ()> {
+    pub fn next(&mut self) -> Result<Span<Token<'a>>, Span<ReadError>> {
         match self.force_take.take() {
             Some(token) => Ok(token),
             None => self.lexer.next(),
         }
     }
+
+    /// Like [Buffered::peek], but lexes the token in argument position.
+    #[inline]
+    pub fn peek_argument(&mut self) -> Result<&Span<Token<'a>>, Span<ReadError>> {
+        if self.force_take.is_some() {
+            return Ok(unsafe {
+                self.force_take.as_ref().unwrap_unchecked()
+            })
+        }
+
+        Ok(self.force_take.insert(self.lexer.next_argument()?))
+    }
+
+    /// Like [Buffered::next], but lexes the token in argument position.
+    #[inline]
+    pub fn next_argument(&mut self) -> Result<Span<Token<'a>>, Span<ReadError>> {
+        match self.force_take.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_argument(),
+        }
+    }
+
+    /// Returns `true` if no token is buffered and the next byte is whitespace.
+    #[inline]
+    pub fn followed_by_whitespace(&self) -> bool {
+        self.force_take.is_none() && self.lexer.at_whitespace()
+    }
+
+    /// Returns the text of a token.
+    #[inline]
+    pub fn source(&self, range: Range<Index>) -> &'a str {
+        self.lexer.source(range)
+    }
+
+    /// Returns `true` if no token is buffered and the next byte is `byte`.
+    #[inline]
+    pub fn immediately_followed_by(&self, byte: u8) -> bool {
+        self.force_take.is_none() && self.lexer.at(byte)
+    }
 }
\ No newline at end of file
@@ -33,6 +33,7 @@ impl<T: Debug> Debug for Span<T> {
     }
 }
 
+#[derive(Clone)]
 pub struct Cursor<'a> {
     start: *const u8,
     next: *const u8,
@@ -86,4 +87,12 @@ impl<'a> Cursor<'a> {
     pub fn pointer(&self) -> *const u8 {
         self.next
     }
+
+    /// Returns the text between two indices of the input.
+    #[inline]
+    pub fn slice(&self, range: Range<Index>) -> &'a str {
+        unsafe {
+            std::str::from_raw_parts(self.start.add(range.start as usize), (range.end - range.start) as usize)
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+use crate::diagnostics::Diagnostic;
+use crate::read::bytes::Index;
+use crate::units::UNITS;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// An error while lexing or parsing the input. Errors are returned as a
+/// [Span](crate::read::bytes::Span) that covers the offending part of the input.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReadError {
+    /// A character that can't start a token.
+    UnexpectedCharacter,
+    UnterminatedString,
+    /// A radix prefix without digits, like `0x`.
+    MissingDigits,
+    /// A digit that is out of range for the radix, like the `2` in `0b102`.
+    InvalidDigit,
+    /// An exponent without digits, like `1e`.
+    MissingExponent,
+    /// An underscore that isn't between two digits, like in `1_`.
+    MisplacedSeparator,
+    /// An integer literal that doesn't fit into 64 bits.
+    IntegerTooLarge,
+    /// A number followed by letters that aren't a unit, like `12px`.
+    UnknownUnit,
+    /// A token that isn't valid at its position.
+    UnexpectedToken,
+    /// A token where a `)` was expected, with the index of the opening `(`.
+    UnclosedParenthesis(Index),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::UnexpectedCharacter => "Unexpected character",
+            Self::UnterminatedString => "Unterminated string",
+            Self::MissingDigits => "Expected digits after the radix prefix",
+            Self::InvalidDigit => "Invalid digit for the radix of the number",
+            Self::MissingExponent => "Expected digits in the exponent",
+            Self::MisplacedSeparator => "A digit separator has to be between two digits",
+            Self::IntegerTooLarge => "Integer literal is too large",
+            Self::UnknownUnit => "Unknown unit",
+            Self::UnexpectedToken => "Unexpected token",
+            Self::UnclosedParenthesis(_) => "Unclosed parenthesis",
+        })
+    }
+}
+
+impl ReadError {
+    /// Returns a diagnostic for the error at the given range of the input.
+    pub fn diagnostic(&self, range: Range<Index>) -> Diagnostic {
+        let diagnostic = Diagnostic::new(self.to_string());
+
+        match self {
+            Self::UnexpectedCharacter => diagnostic.with_primary(range, "not valid here"),
+            Self::UnterminatedString => diagnostic
+                .with_primary(range, "the string starts here")
+                .with_hint("end the string with the same quote it starts with"),
+            Self::MissingDigits => diagnostic
+                .with_primary(range, "no digits after the prefix")
+                .with_hint("hex, binary and octal integers look like `0xff`, `0b1010` and `0o755`"),
+            Self::InvalidDigit => diagnostic.with_primary(range, "not a digit of this radix"),
+            Self::MissingExponent => diagnostic
+                .with_primary(range, "no digits in the exponent")
+                .with_hint("exponents look like `1e9` or `1e-9`"),
+            Self::MisplacedSeparator => diagnostic.with_primary(range, "`_` has to be followed by a digit"),
+            Self::IntegerTooLarge => diagnostic
+                .with_primary(range, "does not fit into 64 bits")
+                .with_hint("write a float like `1e20` for larger numbers"),
+            Self::UnknownUnit => diagnostic
+                .with_primary(range, "not a unit")
+                .with_note(format!(
+                    "the known units are {}",
+                    UNITS.iter().map(|unit| unit.name).collect::<Vec<_>>().join(", ")
+                )),
+            Self::UnexpectedToken => diagnostic.with_primary(range, "unexpected here"),
+            Self::UnclosedParenthesis(open) => diagnostic
+                .with_primary(range, "expected `)`")
+                .with_secondary(*open..*open + 1, "opened here"),
+        }
+    }
+}
\ No newline at end of file
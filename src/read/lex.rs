@@ -1,6 +1,9 @@
+use std::ops::Range;
 use std::str::from_raw_parts;
-use crate::read::bytes::{Cursor, Span};
+use crate::read::bytes::{Cursor, Index, Span};
+use crate::read::error::ReadError;
 use crate::read::tokens::Token;
+use crate::units::find_unit;
 
 pub struct Lexer<'a> {
     cursor: Cursor<'a>,
@@ -12,46 +15,31 @@ impl<'a> Lexer<'a> {
         Self { cursor }
     }
 
-    pub fn next(&mut self) -> Result<Span<Token<'a>>, ()> {
+    /// Returns `true` if the next byte is whitespace.
+    #[inline]
+    pub fn at_whitespace(&self) -> bool {
+        self.cursor.peek().is_some_and(|byte| byte.is_ascii_whitespace())
+    }
+
+    /// Returns `true` if the next byte is `byte`.
+    #[inline]
+    pub fn at(&self, byte: u8) -> bool {
+        self.cursor.peek() == Some(byte)
+    }
+
+    /// Returns the text of a token.
+    #[inline]
+    pub fn source(&self, range: Range<Index>) -> &'a str {
+        self.cursor.slice(range)
+    }
+
+    pub fn next(&mut self) -> Result<Span<Token<'a>>, Span<ReadError>> {
         self.cursor.skip_whitespace();
 
         let start_index = self.cursor.index();
         
         let token = match self.cursor.peek() {
-            Some(first_digit) if matches!(first_digit, b'0'..=b'9') => {
-                let mut number = (first_digit - b'0') as f64;
-
-                self.cursor.advance();
-
-                loop {
-                    match self.cursor.peek() {
-                        Some(x) if matches!(x, b'0'..=b'9') => {
-                            number = number * 10.0 + (x - b'0') as f64;
-                            self.cursor.advance();
-                        },
-                        _ => break,
-                    }
-                }
-
-                if self.cursor.peek() == Some(b'.') {
-                    self.cursor.advance();
-
-                    let mut decimal = 0.1;
-
-                    loop {
-                        match self.cursor.peek() {
-                            Some(x) if matches!(x, b'0'..=b'9') => {
-                                number += (x - b'0') as f64 * decimal;
-                                decimal *= 0.1;
-                                self.cursor.advance();
-                            },
-                            _ => break,
-                        }
-                    }
-                }
-
-                Ok(Token::Number(number))
-            }
+            Some(b'0'..=b'9') => self.lex_number(),
             Some(b'+') => {
                 self.cursor.advance();
                 Ok(Token::Plus)
@@ -62,12 +50,16 @@ impl<'a> Lexer<'a> {
             },
             Some(b'*') => {
                 self.cursor.advance();
-                Ok(Token::Star)
+                Ok(self.lex_followed_by(b'*', Token::Star, Token::StarStar))
             },
             Some(b'/') => {
                 self.cursor.advance();
                 Ok(Token::Slash)
             },
+            Some(b'%') => {
+                self.cursor.advance();
+                Ok(Token::Percent)
+            },
             Some(b'^') => {
                 self.cursor.advance();
                 Ok(Token::Caret)
@@ -76,23 +68,97 @@ impl<'a> Lexer<'a> {
                 self.cursor.advance();
                 Ok(Token::LeftParenthesis)
             },
+            Some(b',') => {
+                self.cursor.advance();
+                Ok(Token::Comma)
+            },
             Some(b')') => {
                 self.cursor.advance();
                 Ok(Token::RightParenthesis)
             },
-            Some(_) => {
+            Some(b'[') => {
+                self.cursor.advance();
+                Ok(Token::LeftBracket)
+            },
+            Some(b']') => {
+                self.cursor.advance();
+                Ok(Token::RightBracket)
+            },
+            Some(b'{') => {
+                self.cursor.advance();
+                Ok(Token::LeftBrace)
+            },
+            Some(b'}') => {
+                self.cursor.advance();
+                Ok(Token::RightBrace)
+            },
+            Some(b'|') => {
+                self.cursor.advance();
+                Ok(self.lex_followed_by(b'|', Token::Pipe, Token::PipePipe))
+            },
+            Some(b'&') => {
+                self.cursor.advance();
+                Ok(self.lex_followed_by(b'&', Token::Ampersand, Token::AmpersandAmpersand))
+            },
+            Some(b'=') => {
+                self.cursor.advance();
+                Ok(self.lex_followed_by(b'=', Token::Equals, Token::EqualsEquals))
+            },
+            Some(b'!') => {
+                self.cursor.advance();
+                Ok(self.lex_followed_by(b'=', Token::ExclamationMark, Token::ExclamationMarkEquals))
+            },
+            Some(b'<') => {
+                self.cursor.advance();
+
+                if self.cursor.peek() == Some(b'<') {
+                    self.cursor.advance();
+                    Ok(Token::LeftAngleLeftAngle)
+                } else {
+                    Ok(self.lex_followed_by(b'=', Token::LeftAngle, Token::LeftAngleEquals))
+                }
+            },
+            Some(b'>') => {
+                self.cursor.advance();
+
+                if self.cursor.peek() == Some(b'>') {
+                    self.cursor.advance();
+                    Ok(Token::RightAngleRightAngle)
+                } else {
+                    Ok(self.lex_followed_by(b'=', Token::RightAngle, Token::RightAngleEquals))
+                }
+            },
+            Some(quote @ (b'"' | b'\'')) => self.lex_string(quote),
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') => {
                 let current = self.cursor.pointer();
 
                 while let Some(byte) = self.cursor.peek() {
-                    if !byte.is_ascii_alphanumeric() && byte != b'_' {
+                    if byte == b'-' || byte == b'.' {
+                        // Allow dashes and dots inside of identifiers like `sort-by`
+                        // or `math.sqrt`, but not as a trailing character.
+                        let mut lookahead = self.cursor.clone();
+                        lookahead.advance();
+
+                        if !lookahead.peek().is_some_and(|byte| byte.is_ascii_alphabetic()) {
+                            break;
+                        }
+                    } else if !byte.is_ascii_alphanumeric() && byte != b'_' {
                         break;
                     }
                     self.cursor.advance();
                 }
                 
-                Ok(Token::Identifier(unsafe {
-                    from_raw_parts(current, self.cursor.pointer() as usize - current as usize)
-                }))
+                Ok(Token::Identifier(self.slice_from(current)))
+            },
+            Some(_) => {
+                self.cursor.advance();
+
+                // Skip the continuation bytes so the error covers the whole character.
+                while let Some(0x80..=0xBF) = self.cursor.peek() {
+                    self.cursor.advance();
+                }
+
+                Err(self.error(ReadError::UnexpectedCharacter, start_index))
             },
             None => Ok(Token::EndOfInput),
         };
@@ -102,6 +168,238 @@ impl<'a> Lexer<'a> {
             range: start_index..self.cursor.index(),
         })
     }
+
+    /// Lexes the next token in argument position, i.e. after the name of a command.
+    ///
+    /// Arguments are split at whitespace, so anything that isn't a number, a string or
+    /// a parenthesis is lexed as a [Token::Word], e.g. `-a`, `src/main.rs` or `*.txt`.
+    /// Operators are only recognized if they are followed by whitespace.
+    pub fn next_argument(&mut self) -> Result<Span<Token<'a>>, Span<ReadError>> {
+        self.cursor.skip_whitespace();
+
+        let start = self.cursor.clone();
+
+        match self.cursor.peek() {
+            None | Some(b'(' | b')' | b'|' | b'"' | b'\'') => return self.next(),
+            Some(b'0'..=b'9') => {
+                if self.lex_number().is_ok() && self.at_argument_end() {
+                    self.cursor = start;
+                    return self.next();
+                }
+            }
+            _ => {
+                if let Ok(token) = self.next() {
+                    if token.value.is_operator() && self.at_argument_end() {
+                        return Ok(token);
+                    }
+                }
+            }
+        }
+
+        self.cursor = start;
+
+        let start_index = self.cursor.index();
+        let current = self.cursor.pointer();
+
+        while !self.at_argument_end() {
+            self.cursor.advance();
+        }
+
+        Ok(Span {
+            value: Token::Word(self.slice_from(current)),
+            range: start_index..self.cursor.index(),
+        })
+    }
+
+    /// Returns `long` and advances if the next byte is `byte`, otherwise returns `short`.
+    #[inline]
+    fn lex_followed_by(&mut self, byte: u8, short: Token<'a>, long: Token<'a>) -> Token<'a> {
+        if self.cursor.peek() == Some(byte) {
+            self.cursor.advance();
+            long
+        } else {
+            short
+        }
+    }
+
+    /// Lexes an integer or, if it has a fractional part or an exponent, a float.
+    ///
+    /// Integers can also be written in hex (`0xFF`), binary (`0b1010`) or octal (`0o755`),
+    /// and digits can be separated by underscores (`1_000_000`). A decimal number directly
+    /// followed by a unit is a quantity, like `3.5GiB`.
+    fn lex_number(&mut self) -> Result<Token<'a>, Span<ReadError>> {
+        let start_index = self.cursor.index();
+        let start = self.cursor.pointer();
+
+        let mut lookahead = self.cursor.clone();
+        lookahead.advance();
+
+        let radix = match (self.cursor.peek(), lookahead.peek()) {
+            (Some(b'0'), Some(b'x' | b'X')) => 16,
+            (Some(b'0'), Some(b'o' | b'O')) => 8,
+            (Some(b'0'), Some(b'b' | b'B')) => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            lookahead.advance();
+            self.cursor = lookahead;
+
+            let digits_start = self.cursor.pointer();
+
+            if self.lex_digits(radix)? == 0 {
+                return Err(self.error(ReadError::MissingDigits, start_index));
+            }
+
+            let digits = self.slice_from(digits_start).replace('_', "");
+
+            return i64::from_str_radix(&digits, radix)
+                .map(Token::Integer)
+                .map_err(|_| self.error(ReadError::IntegerTooLarge, start_index));
+        }
+
+        let mut is_float = false;
+
+        self.lex_digits(10)?;
+
+        if self.cursor.peek() == Some(b'.') {
+            let mut lookahead = self.cursor.clone();
+            lookahead.advance();
+
+            // Only a dot followed by a digit is a decimal point.
+            if let Some(b'0'..=b'9') = lookahead.peek() {
+                self.cursor = lookahead;
+                self.lex_digits(10)?;
+                is_float = true;
+            }
+        }
+
+        if let Some(b'e' | b'E') = self.cursor.peek() {
+            self.cursor.advance();
+
+            if let Some(b'+' | b'-') = self.cursor.peek() {
+                self.cursor.advance();
+            }
+
+            if self.lex_digits(10)? == 0 {
+                return Err(self.error(ReadError::MissingExponent, start_index));
+            }
+
+            is_float = true;
+        }
+
+        let text = self.slice_from(start).replace('_', "");
+
+        let token = if is_float {
+            // The syntax was checked above, so this can't fail.
+            Token::Number(text.parse().unwrap_or(f64::NAN))
+        } else {
+            text.parse()
+                .map(Token::Integer)
+                .map_err(|_| self.error(ReadError::IntegerTooLarge, start_index))?
+        };
+
+        if !self.cursor.peek().is_some_and(|byte| byte.is_ascii_alphabetic()) {
+            return Ok(token);
+        }
+
+        // A unit directly after the number makes it a quantity, like `10MB`.
+        let unit_start_index = self.cursor.index();
+        let unit_start = self.cursor.pointer();
+
+        while self.cursor.peek().is_some_and(|byte| byte.is_ascii_alphabetic()) {
+            self.cursor.advance();
+        }
+
+        let unit = find_unit(self.slice_from(unit_start))
+            .ok_or_else(|| self.error(ReadError::UnknownUnit, unit_start_index))?;
+
+        let amount = match token {
+            Token::Integer(int) => int as f64,
+            Token::Number(num) => num,
+            _ => unreachable!(),
+        };
+
+        Ok(Token::Quantity(amount, unit))
+    }
+
+    /// Skips digits of the given radix and underscores between them, and returns the
+    /// number of digits. Decimal digits that are out of range for the radix are an error.
+    fn lex_digits(&mut self, radix: u32) -> Result<usize, Span<ReadError>> {
+        let mut count = 0;
+
+        loop {
+            let start_index = self.cursor.index();
+
+            match self.cursor.peek() {
+                Some(b'_') => {
+                    self.cursor.advance();
+
+                    let followed_by_digit = self.cursor
+                        .peek()
+                        .is_some_and(|byte| (byte as char).is_digit(radix));
+
+                    if count == 0 || !followed_by_digit {
+                        return Err(self.error(ReadError::MisplacedSeparator, start_index));
+                    }
+                }
+                Some(byte) if (byte as char).is_digit(radix) => {
+                    self.cursor.advance();
+                    count += 1;
+                }
+                Some(b'0'..=b'9') => {
+                    self.cursor.advance();
+                    return Err(self.error(ReadError::InvalidDigit, start_index));
+                }
+                _ => return Ok(count),
+            }
+        }
+    }
+
+    fn lex_string(&mut self, quote: u8) -> Result<Token<'a>, Span<ReadError>> {
+        let start_index = self.cursor.index();
+        self.cursor.advance();
+
+        let current = self.cursor.pointer();
+
+        loop {
+            match self.cursor.peek() {
+                Some(byte) if byte == quote => break,
+                Some(_) => self.cursor.advance(),
+                None => return Err(self.error(ReadError::UnterminatedString, start_index)),
+            }
+        }
+
+        let string = self.slice_from(current);
+        self.cursor.advance();
+
+        Ok(Token::String(string))
+    }
+
+    /// Returns `true` if the cursor is at a position where an argument ends.
+    #[inline]
+    fn at_argument_end(&self) -> bool {
+        match self.cursor.peek() {
+            None => true,
+            Some(byte) => byte.is_ascii_whitespace() || matches!(byte, b'(' | b')' | b'|'),
+        }
+    }
+
+    /// Returns an error that spans from `start_index` to the cursor.
+    #[inline]
+    fn error(&self, error: ReadError, start_index: Index) -> Span<ReadError> {
+        Span {
+            value: error,
+            range: start_index..self.cursor.index(),
+        }
+    }
+
+    #[inline]
+    fn slice_from(&self, start: *const u8) -> &'a str {
+        unsafe {
+            from_raw_parts(start, self.cursor.pointer() as usize - start as usize)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -110,15 +408,51 @@ mod tests {
 
     #[test]
     pub fn test_lex() {
-        let mut lexer = Lexer::new(Cursor::new(" + - * / ** % 789.012"));
-
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Plus, range: 0..1 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Minus, range: 2..3 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Star, range: 4..5 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Slash, range: 6..7 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Star, range: 8..9 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Star, range: 10..11 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::Number(789.012), range: 12..20 }));
-        assert_eq!(lexer.next(), Ok(Span { value: Token::EndOfInput, range: 20..20 }));
+        let mut lexer = Lexer::new(Cursor::new(" + - * / ** % 789.012 42"));
+
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Plus, range: 1..2 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Minus, range: 3..4 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Star, range: 5..6 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Slash, range: 7..8 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::StarStar, range: 9..11 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Percent, range: 12..13 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Number(789.012), range: 14..21 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Integer(42), range: 22..24 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::EndOfInput, range: 24..24 }));
+    }
+
+    #[test]
+    pub fn test_lex_arguments() {
+        let mut lexer = Lexer::new(Cursor::new("-la src/main.rs 12 12px + \"a b\""));
+
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::Word("-la"), range: 0..3 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::Word("src/main.rs"), range: 4..15 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::Integer(12), range: 16..18 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::Word("12px"), range: 19..23 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::Plus, range: 24..25 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::String("a b"), range: 26..31 }));
+        assert_eq!(lexer.next_argument(), Ok(Span { value: Token::EndOfInput, range: 31..31 }));
+    }
+
+    #[test]
+    pub fn test_lex_number_literals() {
+        let mut lexer = Lexer::new(Cursor::new("0xFF 0b1010 0o755 1e-9 1_000_000 2.5E3"));
+
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Integer(255), range: 0..4 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Integer(10), range: 5..11 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Integer(493), range: 12..17 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Number(1e-9), range: 18..22 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Integer(1_000_000), range: 23..32 }));
+        assert_eq!(lexer.next(), Ok(Span { value: Token::Number(2500.0), range: 33..38 }));
+
+        for (input, error, range) in [
+            ("0x", ReadError::MissingDigits, 0..2),
+            ("0b102", ReadError::InvalidDigit, 4..5),
+            ("1e", ReadError::MissingExponent, 0..2),
+            ("1_", ReadError::MisplacedSeparator, 1..2),
+            ("9223372036854775808", ReadError::IntegerTooLarge, 0..19),
+        ] {
+            assert_eq!(Lexer::new(Cursor::new(input)).next(), Err(Span { value: error, range }));
+        }
     }
 }
\ No newline at end of file
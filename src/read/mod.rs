@@ -4,4 +4,5 @@ pub mod lex;
 pub mod bytes;
 pub mod parse;
 pub mod buffered;
+pub mod error;
 mod bp;
\ No newline at end of file
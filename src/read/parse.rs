@@ -1,30 +1,198 @@
 use std::ops::Range;
-use crate::read::ast::{BinaryOperation, Expression};
+use crate::read::ast::{BinaryOperation, Expression, UnaryOperation};
 use crate::read::bp;
 use crate::read::buffered::Buffered;
-use crate::read::bytes::Span;
+use crate::read::bytes::{Cursor, Index, Span};
+use crate::read::error::ReadError;
+use crate::read::lex::Lexer;
 use crate::read::tokens::Token;
+use crate::units::find_unit;
+
+/// Parses a whole input line and returns the first error if it is invalid.
+pub fn parse<'a>(iter: &mut Buffered<'a>) -> Result<Span<Expression<'a>>, Span<ReadError>> {
+    let (expression, mut errors) = parse_recovering(iter);
+
+    if errors.is_empty() {
+        Ok(expression)
+    } else {
+        Err(errors.swap_remove(0))
+    }
+}
+
+/// Parses a whole input line and continues after errors, so that invalid input still
+/// has a syntax tree for highlighting and completion. Invalid parts of the input become
+/// [Expression::Error] nodes, and all errors are returned in the order of the input.
+pub fn parse_recovering<'a>(iter: &mut Buffered<'a>) -> (Span<Expression<'a>>, Vec<Span<ReadError>>) {
+    let mut errors = Vec::new();
+    let expression = parse_pipeline(iter, &mut errors);
+
+    let token = next(iter, &mut errors, false);
+
+    if token.value != Token::EndOfInput {
+        errors.push(unexpected(token));
+
+        while next(iter, &mut errors, false).value != Token::EndOfInput {}
+    }
+
+    (expression, errors)
+}
+
+/// Returns `true` if the input can only become valid by adding to its end, like a
+/// string without its closing quote, an unclosed parenthesis or a trailing `|`.
+pub fn is_incomplete(input: &str) -> bool {
+    if input.trim().is_empty() {
+        return false;
+    }
+
+    let (_, errors) = parse_recovering(&mut Buffered::new(Lexer::new(Cursor::new(input))));
+    let end = input.len() as Index;
+
+    errors.iter().any(|error| match error.value {
+        ReadError::UnterminatedString => true,
+        ReadError::UnexpectedToken | ReadError::UnclosedParenthesis(_) => error.range.start == end,
+        _ => false,
+    })
+}
+
+/// Parses expressions separated by `|`.
+fn parse_pipeline<'a>(iter: &mut Buffered<'a>, errors: &mut Vec<Span<ReadError>>) -> Span<Expression<'a>> {
+    let mut stages = vec![parse_expression(iter, errors, 0, false)];
+
+    loop {
+        let token = peek(iter, errors, false);
+
+        match token.value {
+            Token::Pipe => {}
+            Token::EndOfInput | Token::RightParenthesis | Token::Comma => break,
+            _ => {
+                // Skip the rest of the stage, the next `|` starts a new one.
+                errors.push(unexpected_at(token));
+                skip_stage(iter, errors);
+                continue;
+            }
+        }
+
+        iter.next().expect("a token is buffered");
+        stages.push(parse_expression(iter, errors, 0, false));
+    }
+
+    if stages.len() == 1 {
+        return stages.swap_remove(0);
+    }
+
+    Span {
+        range: stages[0].range.start..stages.last().map_or(0, |stage| stage.range.end),
+        value: Expression::Pipeline(stages),
+    }
+}
+
+/// Parses an expression. If `in_arguments` is true, the expression is an argument
+/// of a command and its operands are lexed in argument position.
+fn parse_expression<'a>(
+    iter: &mut Buffered<'a>,
+    errors: &mut Vec<Span<ReadError>>,
+    min_bp: u8,
+    in_arguments: bool
+) -> Span<Expression<'a>> {
+    let error_count = errors.len();
+    let token = peek(iter, errors, in_arguments);
+
+    if matches!(
+        token.value,
+        Token::EndOfInput | Token::RightParenthesis | Token::Pipe | Token::Comma
+    ) {
+        // The term is missing. The token belongs to an enclosing expression, so it isn't
+        // consumed, and if an invalid character was skipped before it, that's the error.
+        let index = token.range.start;
+
+        if errors.len() == error_count {
+            errors.push(unexpected_at(token));
+        }
+
+        return Span {
+            range: errors[error_count].range.start.min(index)..index,
+            value: Expression::Error,
+        };
+    }
 
-pub fn parse<'a>(iter: &mut Buffered<'a>, min_bp: u8) -> Result<Span<Expression<'a>>, ()> {
     let Span {
         range: Range {
             start: start_index,
             end: end_index
         },
         value
-    } = iter.next()?;
-
-    let mut first_term = Span {
-        value: match value {
-            Token::Identifier(id) => Expression::CommandInvocation(id),
-            Token::Number(num) => Expression::Number(num),
-            Token::String(string) => Expression::String(string),
-            _ => return Err(()),
-        },
-        range: Range {
-            start: start_index,
-            end: end_index
+    } = iter.next().expect("a token is buffered");
+
+    let mut first_term = match value {
+        Token::Identifier(name) if iter.immediately_followed_by(b'(') => {
+            let open = iter.next().expect("`(` follows");
+
+            let (arguments, end_index) = parse_call_arguments(iter, errors, open.range.start);
+
+            Span {
+                range: start_index..end_index,
+                value: Expression::CommandInvocation { name, arguments },
+            }
         }
+        Token::Identifier(name) => {
+            let arguments = if iter.followed_by_whitespace() {
+                parse_arguments(iter, errors)
+            } else {
+                Vec::new()
+            };
+
+            Span {
+                range: start_index..arguments.last().map_or(end_index, |argument| argument.range.end),
+                value: Expression::CommandInvocation { name, arguments },
+            }
+        }
+        Token::LeftParenthesis => {
+            let inner = parse_pipeline(iter, errors);
+            let end_index = expect_closing(iter, errors, start_index).unwrap_or(inner.range.end);
+
+            Span {
+                value: inner.value,
+                range: start_index..end_index,
+            }
+        }
+        Token::Minus | Token::ExclamationMark => {
+            let operand = parse_expression(iter, errors, bp::NEGATE_AND_NOT, in_arguments);
+
+            Span {
+                range: start_index..operand.range.end,
+                value: Expression::Unary {
+                    operation: if value == Token::Minus {
+                        UnaryOperation::Negate
+                    } else {
+                        UnaryOperation::Not
+                    },
+                    operand: Box::new(operand),
+                },
+            }
+        }
+        value => Span {
+            value: match value {
+                Token::Number(num) => numeral(Expression::Number(num), iter, start_index..end_index, in_arguments),
+                Token::Integer(int) => numeral(Expression::Integer(int), iter, start_index..end_index, in_arguments),
+                Token::Quantity(amount, unit) => {
+                    numeral(Expression::Quantity(amount, unit), iter, start_index..end_index, in_arguments)
+                }
+                Token::String(string) => Expression::String(string),
+                Token::Word(word) => Expression::Word(word),
+                _ => {
+                    errors.push(Span {
+                        value: ReadError::UnexpectedToken,
+                        range: start_index..end_index,
+                    });
+
+                    Expression::Error
+                }
+            },
+            range: Range {
+                start: start_index,
+                end: end_index
+            }
+        },
     };
 
     macro_rules! op {
@@ -33,9 +201,9 @@ pub fn parse<'a>(iter: &mut Buffered<'a>, min_bp: u8) -> Result<Span<Expression<
                 break;
             }
 
-            iter.next()?;
+            iter.next().expect("a token is buffered");
 
-            let right = parse(iter, $bp.1)?;
+            let right = parse_expression(iter, errors, $bp.1, in_arguments);
 
             (
                 right.range.end,
@@ -49,14 +217,66 @@ pub fn parse<'a>(iter: &mut Buffered<'a>, min_bp: u8) -> Result<Span<Expression<
     }
 
     loop {
-        let token = iter.peek()?;
+        let token = peek(iter, errors, in_arguments);
 
         let (end, value) = match &token.value {
             Token::Plus => op!(BinaryOperation::Add, bp::ADDITIVE),
+            Token::Minus => op!(BinaryOperation::Subtract, bp::ADDITIVE),
             Token::Star => op!(BinaryOperation::Multiply, bp::MULTIPLICATIVE),
             Token::Slash => op!(BinaryOperation::Divide, bp::MULTIPLICATIVE),
-            Token::EndOfInput => break,
-            _ => return Err(())
+            Token::Percent => op!(BinaryOperation::Modulo, bp::MULTIPLICATIVE),
+            Token::StarStar => op!(BinaryOperation::Exponentiate, bp::EXPONENTIAL),
+            Token::Ampersand => op!(BinaryOperation::BitwiseAnd, bp::BITWISE_AND),
+            Token::Caret => op!(BinaryOperation::BitwiseXor, bp::BITWISE_XOR),
+            Token::LeftAngleLeftAngle => op!(BinaryOperation::BitwiseShiftLeft, bp::SHIFT),
+            Token::RightAngleRightAngle => op!(BinaryOperation::BitwiseShiftRight, bp::SHIFT),
+            Token::EqualsEquals => op!(BinaryOperation::Equal, bp::EQUALITY),
+            Token::ExclamationMarkEquals => op!(BinaryOperation::NotEqual, bp::EQUALITY),
+            Token::LeftAngle => op!(BinaryOperation::LessThan, bp::RELATIONAL),
+            Token::LeftAngleEquals => op!(BinaryOperation::LessThanOrEqual, bp::RELATIONAL),
+            Token::RightAngle => op!(BinaryOperation::GreaterThan, bp::RELATIONAL),
+            Token::RightAngleEquals => op!(BinaryOperation::GreaterThanOrEqual, bp::RELATIONAL),
+            Token::AmpersandAmpersand => op!(BinaryOperation::LogicalAnd, bp::LOGICAL_AND),
+            Token::PipePipe => op!(BinaryOperation::LogicalOr, bp::LOGICAL_OR),
+            Token::Identifier("to") => {
+                if bp::CONVERSION < min_bp {
+                    break;
+                }
+
+                iter.next().expect("a token is buffered");
+
+                let token = peek(iter, errors, false);
+
+                let unit = match token.value {
+                    Token::Identifier(name) => find_unit(name),
+                    _ => None,
+                };
+
+                match unit {
+                    Some(unit) => (
+                        iter.next().expect("a token is buffered").range.end,
+                        Expression::Conversion {
+                            operand: Box::new(first_term),
+                            unit,
+                        }
+                    ),
+                    None => {
+                        errors.push(Span {
+                            value: ReadError::UnknownUnit,
+                            range: token.range.clone(),
+                        });
+
+                        // Only a misspelled unit belongs to the conversion.
+                        let end = match token.value {
+                            Token::Identifier(_) => iter.next().expect("a token is buffered").range.end,
+                            _ => first_term.range.end,
+                        };
+
+                        (end, Expression::Error)
+                    }
+                }
+            }
+            _ => break,
         };
 
         first_term = Span {
@@ -65,5 +285,234 @@ pub fn parse<'a>(iter: &mut Buffered<'a>, min_bp: u8) -> Result<Span<Expression<
         };
     }
 
-    Ok(first_term)
+    first_term
+}
+
+/// Keeps the text of a number in argument position, see [Expression::Numeral].
+#[inline]
+fn numeral<'a>(number: Expression<'a>, iter: &Buffered<'a>, range: Range<Index>, in_arguments: bool) -> Expression<'a> {
+    match in_arguments {
+        true => Expression::Numeral {
+            text: iter.source(range.clone()),
+            value: Box::new(Span { value: number, range }),
+        },
+        false => number,
+    }
+}
+
+/// Parses the arguments of a command invocation up to the end of the command.
+fn parse_arguments<'a>(iter: &mut Buffered<'a>, errors: &mut Vec<Span<ReadError>>) -> Vec<Span<Expression<'a>>> {
+    let mut arguments = Vec::new();
+
+    loop {
+        let token = peek(iter, errors, true);
+
+        if token.value.is_operator() || matches!(
+            token.value,
+            Token::EndOfInput | Token::RightParenthesis | Token::Pipe
+        ) {
+            break;
+        }
+
+        arguments.push(parse_expression(iter, errors, 0, true));
+    }
+
+    arguments
+}
+
+/// Parses the comma separated arguments of a call like `math.hex(255)` after the opening
+/// parenthesis at `open`. Returns the arguments and the end index of the call, which is
+/// the end of the last argument if the closing parenthesis is missing.
+fn parse_call_arguments<'a>(
+    iter: &mut Buffered<'a>,
+    errors: &mut Vec<Span<ReadError>>,
+    open: Index
+) -> (Vec<Span<Expression<'a>>>, Index) {
+    let mut arguments = Vec::new();
+
+    if peek(iter, errors, false).value != Token::RightParenthesis {
+        loop {
+            arguments.push(parse_pipeline(iter, errors));
+
+            if peek(iter, errors, false).value != Token::Comma {
+                break;
+            }
+
+            iter.next().expect("a token is buffered");
+        }
+    }
+
+    let end_index = arguments.last().map_or(open + 1, |argument| argument.range.end);
+
+    (arguments, expect_closing(iter, errors, open).unwrap_or(end_index))
+}
+
+/// Consumes the `)` that closes the parenthesis at `open` and returns its end index.
+/// If another token follows, it is left for the enclosing expression.
+fn expect_closing(iter: &mut Buffered, errors: &mut Vec<Span<ReadError>>, open: Index) -> Option<Index> {
+    let token = peek(iter, errors, false);
+
+    if token.value == Token::RightParenthesis {
+        return iter.next().ok().map(|token| token.range.end);
+    }
+
+    errors.push(Span {
+        value: ReadError::UnclosedParenthesis(open),
+        range: token.range.clone(),
+    });
+
+    None
+}
+
+/// Skips the tokens up to the end of the current pipeline stage, which is the next `|`,
+/// or a `)`, `,` or the end of the input that isn't nested in parentheses.
+fn skip_stage(iter: &mut Buffered, errors: &mut Vec<Span<ReadError>>) {
+    let mut depth = 0usize;
+
+    loop {
+        match peek(iter, errors, false).value {
+            Token::EndOfInput => return,
+            Token::Pipe | Token::RightParenthesis | Token::Comma if depth == 0 => return,
+            Token::LeftParenthesis => depth += 1,
+            Token::RightParenthesis => depth -= 1,
+            _ => {}
+        }
+
+        iter.next().expect("a token is buffered");
+    }
+}
+
+/// Returns the next token. Characters that can't be lexed are recorded as errors and skipped.
+fn next<'a>(iter: &mut Buffered<'a>, errors: &mut Vec<Span<ReadError>>, in_arguments: bool) -> Span<Token<'a>> {
+    loop {
+        let result = if in_arguments { iter.next_argument() } else { iter.next() };
+
+        match result {
+            Ok(token) => return token,
+            Err(error) => errors.push(error),
+        }
+    }
+}
+
+/// Like [next], but leaves the token in the buffer.
+fn peek<'i, 'a>(
+    iter: &'i mut Buffered<'a>,
+    errors: &mut Vec<Span<ReadError>>,
+    in_arguments: bool
+) -> &'i Span<Token<'a>> {
+    loop {
+        let result = if in_arguments { iter.peek_argument() } else { iter.peek() };
+
+        match result {
+            Ok(_) => break,
+            Err(error) => errors.push(error),
+        }
+    }
+
+    iter.peek().expect("a token is buffered")
+}
+
+#[inline]
+fn unexpected(token: Span<Token>) -> Span<ReadError> {
+    Span {
+        value: ReadError::UnexpectedToken,
+        range: token.range,
+    }
+}
+
+#[inline]
+fn unexpected_at(token: &Span<Token>) -> Span<ReadError> {
+    Span {
+        value: ReadError::UnexpectedToken,
+        range: token.range.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(input: &str) -> Vec<Span<ReadError>> {
+        parse_recovering(&mut Buffered::new(Lexer::new(Cursor::new(input)))).1
+    }
+
+    #[test]
+    pub fn test_parse_recovering() {
+        let error = |value, range| Span { value, range };
+
+        assert_eq!(errors("ls | length"), vec![]);
+        assert_eq!(errors("1 +"), vec![error(ReadError::UnexpectedToken, 3..3)]);
+        assert_eq!(errors("1 + $ 2"), vec![error(ReadError::UnexpectedCharacter, 4..5)]);
+        assert_eq!(
+            errors("(1 + * 2 | sqrt(4, 12px"),
+            vec![
+                error(ReadError::UnexpectedToken, 5..6),
+                error(ReadError::UnexpectedToken, 7..8),
+                error(ReadError::UnknownUnit, 21..23),
+                error(ReadError::UnclosedParenthesis(15), 23..23),
+                error(ReadError::UnclosedParenthesis(0), 23..23),
+            ]
+        );
+        assert_eq!(
+            errors("1 2 | 3 to px) 4"),
+            vec![
+                error(ReadError::UnexpectedToken, 2..3),
+                error(ReadError::UnknownUnit, 11..13),
+                error(ReadError::UnexpectedToken, 13..14),
+            ]
+        );
+
+        let mut iter = Buffered::new(Lexer::new(Cursor::new("max(1, ) | math.sqrt")));
+        let (expression, errors) = parse_recovering(&mut iter);
+
+        assert_eq!(errors, vec![error(ReadError::UnexpectedToken, 7..8)]);
+        assert!(matches!(
+            expression.value,
+            Expression::Pipeline(ref stages) if matches!(
+                stages[0].value,
+                Expression::CommandInvocation { name: "max", ref arguments } if matches!(arguments[1].value, Expression::Error)
+            )
+        ));
+    }
+
+    #[test]
+    pub fn test_parse_numerals() {
+        let mut iter = Buffered::new(Lexer::new(Cursor::new("cat 01 1.50 0x10 10.123MB 1 + 2")));
+        let expression = parse(&mut iter).unwrap();
+
+        let Expression::CommandInvocation { name: "cat", arguments } = expression.value else {
+            panic!("expected a command invocation");
+        };
+
+        let texts = arguments
+            .iter()
+            .map(|argument| match argument.value {
+                Expression::Numeral { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(texts, vec![Some("01"), Some("1.50"), Some("0x10"), Some("10.123MB"), None]);
+        assert!(matches!(
+            arguments[0].value,
+            Expression::Numeral { ref value, .. } if matches!(value.value, Expression::Integer(1))
+        ));
+
+        let expression = parse(&mut Buffered::new(Lexer::new(Cursor::new("01 + 1")))).unwrap();
+        assert!(matches!(
+            expression.value,
+            Expression::Binary { ref left, .. } if matches!(left.value, Expression::Integer(1))
+        ));
+    }
+
+    #[test]
+    pub fn test_is_incomplete() {
+        for input in ["ls |", "(1 +\n2", "echo \"a\nb", "max(1,", "1 +  "] {
+            assert!(is_incomplete(input), "{:?}", input);
+        }
+
+        for input in ["ls | length", "1 + )", "ls | | length", "1 2", "", "  "] {
+            assert!(!is_incomplete(input), "{:?}", input);
+        }
+    }
 }
\ No newline at end of file
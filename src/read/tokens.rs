@@ -1,3 +1,5 @@
+use crate::units::Unit;
+
 #[derive(Debug, PartialEq)]
 pub enum Token<'a> {
     Plus,
@@ -40,7 +42,10 @@ pub enum Token<'a> {
     EqualsEquals,
 
     Number(f64),
+    Integer(i64),
+    Quantity(f64, &'static Unit),
     Identifier(&'a str),
+    Word(&'a str),
     Keyword(Keyword),
     String(&'a str),
 
@@ -54,6 +59,33 @@ pub enum Token<'a> {
     EndOfInput,
 }
 
+impl Token<'_> {
+    /// Returns `true` if the token is a prefix or infix operator.
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            Token::Plus
+                | Token::Minus
+                | Token::Star
+                | Token::Slash
+                | Token::StarStar
+                | Token::Percent
+                | Token::Caret
+                | Token::Ampersand
+                | Token::LeftAngleLeftAngle
+                | Token::RightAngleRightAngle
+                | Token::EqualsEquals
+                | Token::ExclamationMarkEquals
+                | Token::LeftAngle
+                | Token::LeftAngleEquals
+                | Token::RightAngle
+                | Token::RightAngleEquals
+                | Token::AmpersandAmpersand
+                | Token::PipePipe
+        )
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Keyword {
     Nil,
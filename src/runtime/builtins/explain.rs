@@ -0,0 +1,103 @@
+use crate::assistant::{Failure, Model};
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::{RuntimeError, Scope, Value};
+use crossterm::style::{Color, SetForegroundColor};
+use std::io::{stdout, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long to wait for the next part of the explanation. Loading a model can take a while.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Explains why the last failed input failed and how to fix it, with the model that
+/// Ollama runs, see [crate::assistant]. The explanation is printed as it streams in.
+///
+/// Problems with the model are printed instead of failing, so that `explain` doesn't
+/// become the last failed input itself.
+pub fn explain(scope: &mut Scope, arguments: &[Span<Expression>], _: Value) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    let Some(failure) = Failure::last(scope) else {
+        println!("Nothing to explain, no input failed yet.");
+        return Ok(Value::Nil);
+    };
+
+    stream(failure.explain(&Model::from_scope(scope)), &mut stdout()).map_err(|_| RuntimeError::IoError)?;
+    Ok(Value::Nil)
+}
+
+/// Writes the parts of the explanation as they arrive, or the problem with the model in red.
+fn stream(receiver: Receiver<Result<String, String>>, out: &mut impl Write) -> std::io::Result<()> {
+    let mut written = false;
+
+    loop {
+        let error = match receiver.recv_timeout(TIMEOUT) {
+            Ok(Ok(text)) => {
+                write!(out, "{}", text)?;
+                out.flush()?;
+                written = true;
+                continue;
+            }
+            Ok(Err(error)) => error,
+            Err(RecvTimeoutError::Timeout) => format!("Ollama didn't answer within {} seconds", TIMEOUT.as_secs()),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if written {
+            writeln!(out)?;
+        }
+
+        writeln!(out, "{}{}{}", SetForegroundColor(Color::Red), error, SetForegroundColor(Color::Reset))?;
+        return writeln!(out, "Check that Ollama runs there, or change the `ollama-url` setting.");
+    }
+
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{new_root_scope, set_variable};
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    pub fn test_explain() {
+        let mut scope = new_root_scope();
+        assert!(matches!(explain(&mut scope, &[], Value::Nil), Ok(Value::Nil)));
+
+        Failure { input: "cargo biuld".to_string(), status: 101, errors: String::new() }.remember(&mut scope);
+
+        // Nothing listens on the port of a closed listener, which is printed instead of failing.
+        let url = format!("http://{}", TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap());
+        set_variable(&mut scope, "ollama-url", Value::String(url));
+
+        assert!(matches!(explain(&mut scope, &[], Value::Nil), Ok(Value::Nil)));
+    }
+
+    #[test]
+    pub fn test_stream() {
+        let streamed = |parts: Vec<Result<&str, &str>>| {
+            let (sender, receiver) = channel();
+
+            for part in parts {
+                sender.send(part.map(str::to_string).map_err(str::to_string)).unwrap();
+            }
+
+            drop(sender);
+
+            let mut out = Vec::new();
+            stream(receiver, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(streamed(vec![Ok("It's "), Ok("`cargo build`.")]), "It's `cargo build`.\n");
+        assert_eq!(
+            streamed(vec![Ok("It's"), Err("Failed to reach Ollama")]),
+            "It's\n\x1b[38;5;9mFailed to reach Ollama\x1b[39m\nCheck that Ollama runs there, or change the `ollama-url` setting.\n"
+        );
+    }
+}
\ No newline at end of file
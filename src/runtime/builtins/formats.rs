@@ -0,0 +1,87 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::builtins::column_argument;
+use crate::runtime::formats::{csv, json, toml, FormatError};
+use crate::runtime::{eval, RuntimeError, Scope, Value};
+use std::path::Path;
+
+/// The formats supported by `open`, `from` and `to`.
+#[derive(Copy, Clone)]
+enum Format {
+    Json,
+    Csv,
+    Toml,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, text: &str) -> Result<Value, RuntimeError> {
+        match self {
+            Self::Json => json::parse(text),
+            Self::Csv => csv::parse(text),
+            Self::Toml => toml::parse(text),
+        }.map_err(|error: FormatError| error.into_runtime_error(text))
+    }
+
+    fn write(self, value: &Value) -> Result<String, RuntimeError> {
+        match self {
+            Self::Json => {
+                let mut out = String::new();
+                json::write(value, &mut out);
+                Ok(out)
+            }
+            Self::Csv => csv::write(value),
+            Self::Toml => toml::write(value),
+        }
+    }
+}
+
+/// Reads a file. If the extension is a known format, the content is parsed,
+/// otherwise it is returned as a string.
+pub fn open(scope: &mut Scope, arguments: &[Span<Expression>], _: Value) -> Result<Value, RuntimeError> {
+    let [argument] = arguments else {
+        return Err(RuntimeError::InvalidArgument);
+    };
+
+    let path = eval(scope, argument)?.to_argument();
+    let text = std::fs::read_to_string(&path).map_err(|_| RuntimeError::IoError)?;
+
+    match Path::new(&path)
+        .extension()
+        .and_then(|extension| Format::from_name(&extension.to_string_lossy())) {
+        Some(format) => format.parse(&text),
+        None => Ok(Value::String(text)),
+    }
+}
+
+/// Parses the input string in the given format, like `from json`.
+pub fn from(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let format = format_argument(scope, arguments)?;
+
+    match input {
+        Value::String(text) => format.parse(&text),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Writes the input value as a string in the given format, like `to json`.
+pub fn to(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let format = format_argument(scope, arguments)?;
+    format.write(&input).map(Value::String)
+}
+
+fn format_argument(scope: &mut Scope, arguments: &[Span<Expression>]) -> Result<Format, RuntimeError> {
+    let [argument] = arguments else {
+        return Err(RuntimeError::InvalidArgument);
+    };
+
+    Format::from_name(&column_argument(scope, argument)?).ok_or(RuntimeError::InvalidArgument)
+}
\ No newline at end of file
@@ -0,0 +1,197 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::{eval, RuntimeError, Scope, Table, Value};
+use crate::units::Quantity;
+use std::fs::{read_dir, Metadata};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lists the entries of a directory as a table.
+///
+/// Supported flags are `-a` (`--all`) to include hidden entries and `-l` (`--long`)
+/// to include the permissions. The optional path defaults to the current directory.
+pub fn ls(scope: &mut Scope, arguments: &[Span<Expression>], _: Value) -> Result<Value, RuntimeError> {
+    let mut all = false;
+    let mut long = false;
+    let mut path = None;
+
+    for argument in arguments {
+        match eval(scope, argument)? {
+            Value::String(flag) if flag == "--all" => all = true,
+            Value::String(flag) if flag == "--long" => long = true,
+            Value::String(flags) if flags.len() > 1 && flags.starts_with('-') => {
+                for flag in flags[1..].chars() {
+                    match flag {
+                        'a' => all = true,
+                        'l' => long = true,
+                        _ => return Err(RuntimeError::InvalidArgument),
+                    }
+                }
+            }
+            value if path.is_none() => path = Some(value.to_argument()),
+            _ => return Err(RuntimeError::InvalidArgument),
+        }
+    }
+
+    let entries = read_dir(path.as_deref().unwrap_or("."))
+        .map_err(|_| RuntimeError::IoError)?;
+
+    let mut columns = vec![
+        "name".to_string(),
+        "type".to_string(),
+        "size".to_string(),
+        "modified".to_string(),
+    ];
+
+    if long {
+        columns.push("permissions".to_string());
+    }
+
+    let mut table = Table::new(columns);
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if !all && name.starts_with('.') {
+            continue;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        let file_type = metadata.file_type();
+
+        let mut row = vec![
+            Value::String(name),
+            Value::String(if file_type.is_dir() {
+                "dir"
+            } else if file_type.is_symlink() {
+                "symlink"
+            } else {
+                "file"
+            }.to_string()),
+            Value::Quantity(Quantity::size(metadata.len())),
+            metadata.modified().map_or(Value::Nil, |time| Value::String(format_time(time))),
+        ];
+
+        if long {
+            row.push(Value::String(permissions(&metadata)));
+        }
+
+        table.rows.push(row);
+    }
+
+    table.rows.sort_by(|a, b| a[0].to_argument().cmp(&b[0].to_argument()));
+
+    Ok(Value::Table(table))
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM` in UTC, so it sorts correctly as a string.
+fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+
+    let (days, seconds_of_day) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60
+    )
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+
+    (0..9)
+        .map(|i| if mode & (1 << (8 - i)) != 0 {
+            ['r', 'w', 'x'][i % 3]
+        } else {
+            '-'
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn permissions(metadata: &Metadata) -> String {
+    if metadata.permissions().readonly() {
+        "readonly".to_string()
+    } else {
+        "readwrite".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{new_root_scope, run_input};
+    use std::time::Duration;
+
+    fn run(input: &str) -> Result<Value, RuntimeError> {
+        run_input(&mut new_root_scope(), input, Value::Nil)
+    }
+
+    #[test]
+    pub fn test_ls() {
+        let directory = std::env::temp_dir().join(format!("shesh-test-ls-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        std::fs::create_dir_all(directory.join("src")).unwrap();
+        std::fs::write(directory.join("notes.txt"), "abc").unwrap();
+        std::fs::write(directory.join(".hidden"), "").unwrap();
+
+        let list = |flags: &str| match run(&format!("ls {} '{}'", flags, directory.display())) {
+            Ok(Value::Table(table)) => table,
+            result => panic!("expected a table, got {:?}", result),
+        };
+
+        let table = list("");
+        assert_eq!(table.columns, ["name", "type", "size", "modified"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][0], Value::String("notes.txt".to_string()));
+        assert_eq!(table.rows[0][1], Value::String("file".to_string()));
+        assert_eq!(table.rows[0][2], Value::Quantity(Quantity::size(3)));
+        assert_eq!(table.rows[1][0], Value::String("src".to_string()));
+        assert_eq!(table.rows[1][1], Value::String("dir".to_string()));
+
+        let table = list("-la");
+        assert_eq!(table.columns.last().map(String::as_str), Some("permissions"));
+        assert_eq!(table.rows[0][0], Value::String(".hidden".to_string()));
+        assert_eq!(table.rows.len(), 3);
+
+        assert_eq!(list("--all").rows.len(), 3);
+        assert!(matches!(run(&format!("ls -x '{}'", directory.display())), Err(RuntimeError::InvalidArgument)));
+        assert!(matches!(run(&format!("ls '{}'", directory.join("missing").display())), Err(RuntimeError::IoError)));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    pub fn test_format_time() {
+        let time = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+
+        assert_eq!(format_time(time(0)), "1970-01-01 00:00");
+        assert_eq!(format_time(time(951_782_400 + 3_661)), "2000-02-29 01:01");
+        assert_eq!(format_time(time(1_735_689_599)), "2024-12-31 23:59");
+        assert_eq!(format_time(UNIX_EPOCH - Duration::from_secs(60)), "1970-01-01 00:00");
+    }
+}
\ No newline at end of file
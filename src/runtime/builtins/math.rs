@@ -0,0 +1,463 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::{eval, Builtin, RuntimeError, Scope, Value, Variable};
+use std::cmp::Ordering;
+
+/// The functions of the `math` namespace. They are only available with the prefix, so
+/// they don't hide programs like `exp` or `log`.
+const FUNCTIONS: &[(&str, Builtin)] = &[
+    ("sqrt", sqrt),
+    ("cbrt", cbrt),
+    ("exp", exp),
+    ("ln", ln),
+    ("log", log),
+    ("log2", log2),
+    ("sin", sin),
+    ("cos", cos),
+    ("tan", tan),
+    ("asin", asin),
+    ("acos", acos),
+    ("atan", atan),
+    ("atan2", atan2),
+    ("sinh", sinh),
+    ("cosh", cosh),
+    ("tanh", tanh),
+    ("asinh", asinh),
+    ("acosh", acosh),
+    ("atanh", atanh),
+    ("abs", abs),
+    ("floor", floor),
+    ("ceil", ceil),
+    ("round", round),
+    ("min", min),
+    ("max", max),
+    ("gcd", gcd),
+    ("lcm", lcm),
+    ("factorial", factorial),
+    ("perm", perm),
+    ("comb", comb),
+];
+
+/// The formatters of integers, like `hex(255)`. They are in the `math` namespace and
+/// available without the prefix too.
+const FORMATTERS: &[(&str, Builtin)] = &[
+    ("hex", hex),
+    ("bin", bin),
+    ("oct", oct),
+];
+
+/// Registers the functions as a `math` record, with the constants `math.pi` and `math.e`,
+/// and the formatters without the prefix.
+pub fn register(scope: &mut Scope) {
+    let mut fields = vec![
+        ("pi".to_string(), Value::Number(std::f64::consts::PI)),
+        ("e".to_string(), Value::Number(std::f64::consts::E)),
+    ];
+
+    for &(name, function) in FUNCTIONS.iter().chain(FORMATTERS) {
+        fields.push((name.to_string(), Value::Function(function)));
+    }
+
+    for &(name, function) in FORMATTERS {
+        scope.insert(name.to_string(), Variable {
+            mutable: false,
+            value: Value::Function(function),
+        });
+    }
+
+    scope.insert("math".to_string(), Variable {
+        mutable: false,
+        value: Value::Record(fields),
+    });
+}
+
+/// Returns `true` if the name is a math function, like `math.sqrt`, or a formatter.
+pub fn is_pure(name: &str) -> bool {
+    let is_formatter = |name| FORMATTERS.iter().any(|&(function, _)| function == name);
+
+    match name.strip_prefix("math.") {
+        Some(name) => is_formatter(name) || FUNCTIONS.iter().any(|&(function, _)| function == name),
+        None => is_formatter(name),
+    }
+}
+
+/// Evaluates exactly `N` arguments. If there are none, the input is used as the only argument.
+fn values<const N: usize>(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value
+) -> Result<[Value; N], RuntimeError> {
+    let values = if arguments.is_empty() && input != Value::Nil {
+        vec![input]
+    } else {
+        arguments
+            .iter()
+            .map(|argument| eval(scope, argument))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    values
+        .try_into()
+        .map_err(|_| RuntimeError::InvalidArgument)
+}
+
+/// Evaluates exactly `N` arguments that have to be numbers or integers.
+fn floats<const N: usize>(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value
+) -> Result<[f64; N], RuntimeError> {
+    let values = values::<N>(scope, arguments, input)?;
+    let mut floats = [0.0; N];
+
+    for (float, value) in floats.iter_mut().zip(&values) {
+        *float = value.as_float().ok_or(RuntimeError::TypeError)?;
+    }
+
+    Ok(floats)
+}
+
+/// Evaluates exactly `N` arguments that have to be integers.
+fn ints<const N: usize>(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value
+) -> Result<[i64; N], RuntimeError> {
+    let values = values::<N>(scope, arguments, input)?;
+    let mut ints = [0; N];
+
+    for (int, value) in ints.iter_mut().zip(values) {
+        *int = match value {
+            Value::Int(value) => value,
+            _ => return Err(RuntimeError::TypeError),
+        };
+    }
+
+    Ok(ints)
+}
+
+/// Defines builtins that apply a float function to their arguments.
+macro_rules! float_functions {
+    ($($(#[$doc: meta])* $name: ident($($x: ident),+) => $body: expr;)+) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(
+                scope: &mut Scope,
+                arguments: &[Span<Expression>],
+                input: Value
+            ) -> Result<Value, RuntimeError> {
+                let [$($x),+] = floats(scope, arguments, input)?;
+                Ok(Value::Number($body))
+            }
+        )+
+    };
+}
+
+float_functions! {
+    sqrt(x) => x.sqrt();
+    cbrt(x) => x.cbrt();
+    exp(x) => x.exp();
+    /// The natural logarithm.
+    ln(x) => x.ln();
+    log2(x) => x.log2();
+    sin(x) => x.sin();
+    cos(x) => x.cos();
+    tan(x) => x.tan();
+    asin(x) => x.asin();
+    acos(x) => x.acos();
+    atan(x) => x.atan();
+    /// The angle of the point `(x, y)`, called as `atan2(y, x)`.
+    atan2(y, x) => y.atan2(x);
+    sinh(x) => x.sinh();
+    cosh(x) => x.cosh();
+    tanh(x) => x.tanh();
+    asinh(x) => x.asinh();
+    acosh(x) => x.acosh();
+    atanh(x) => x.atanh();
+}
+
+/// The logarithm to base 10, or to the base given as the second argument.
+pub fn log(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    if arguments.len() == 2 {
+        let [x, base] = floats(scope, arguments, input)?;
+        Ok(Value::Number(x.log(base)))
+    } else {
+        let [x] = floats(scope, arguments, input)?;
+        Ok(Value::Number(x.log10()))
+    }
+}
+
+/// The absolute value, an integer for integers.
+pub fn abs(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    match values(scope, arguments, input)? {
+        [Value::Int(int)] => int.checked_abs().map(Value::Int).ok_or(RuntimeError::IntegerOverflow),
+        [Value::Number(num)] => Ok(Value::Number(num.abs())),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Defines builtins that round a number to an integer. Integers are returned as they
+/// are, and results that don't fit into an integer stay numbers.
+macro_rules! rounding_functions {
+    ($($(#[$doc: meta])* $name: ident => $round: ident;)+) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(
+                scope: &mut Scope,
+                arguments: &[Span<Expression>],
+                input: Value
+            ) -> Result<Value, RuntimeError> {
+                match values(scope, arguments, input)? {
+                    [Value::Int(int)] => Ok(Value::Int(int)),
+                    [Value::Number(num)] => Ok(to_int(num.$round())),
+                    _ => Err(RuntimeError::TypeError),
+                }
+            }
+        )+
+    };
+}
+
+rounding_functions! {
+    floor => floor;
+    ceil => ceil;
+    /// Rounds half-way cases away from zero.
+    round => round;
+}
+
+/// Converts a whole float into an integer if it fits.
+fn to_int(num: f64) -> Value {
+    if num >= i64::MIN as f64 && num < i64::MAX as f64 {
+        Value::Int(num as i64)
+    } else {
+        Value::Number(num)
+    }
+}
+
+/// The smallest of the arguments, or of the items of a list passed as the input.
+pub fn min(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    extremum(scope, arguments, input, Ordering::Less)
+}
+
+/// The largest of the arguments, or of the items of a list passed as the input.
+pub fn max(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    extremum(scope, arguments, input, Ordering::Greater)
+}
+
+fn extremum(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value,
+    wanted: Ordering
+) -> Result<Value, RuntimeError> {
+    let values = match (arguments, input) {
+        ([], Value::List(items)) => items,
+        _ => arguments
+            .iter()
+            .map(|argument| eval(scope, argument))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let mut values = values.into_iter();
+    let mut extremum = values.next().ok_or(RuntimeError::InvalidArgument)?;
+
+    for value in values {
+        match value.compare(&extremum) {
+            Some(ordering) if ordering == wanted => extremum = value,
+            Some(_) => {}
+            None => return Err(RuntimeError::TypeError),
+        }
+    }
+
+    Ok(extremum)
+}
+
+/// The greatest common divisor, which is never negative.
+pub fn gcd(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [a, b] = ints(scope, arguments, input)?;
+
+    i64::try_from(gcd_of(a, b)).map(Value::Int).map_err(|_| RuntimeError::IntegerOverflow)
+}
+
+/// The least common multiple, which is never negative.
+pub fn lcm(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [a, b] = ints(scope, arguments, input)?;
+
+    if a == 0 || b == 0 {
+        return Ok(Value::Int(0));
+    }
+
+    (a.unsigned_abs() / gcd_of(a, b))
+        .checked_mul(b.unsigned_abs())
+        .and_then(|lcm| i64::try_from(lcm).ok())
+        .map(Value::Int)
+        .ok_or(RuntimeError::IntegerOverflow)
+}
+
+fn gcd_of(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// The product of the integers from one to `n`.
+pub fn factorial(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [n] = ints(scope, arguments, input)?;
+
+    if n < 0 {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    (1..=n)
+        .try_fold(1i64, |product, i| product.checked_mul(i))
+        .map(Value::Int)
+        .ok_or(RuntimeError::IntegerOverflow)
+}
+
+/// The number of ordered arrangements of `k` out of `n` items.
+pub fn perm(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [n, k] = ints(scope, arguments, input)?;
+
+    if n < 0 || k < 0 {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    if k > n {
+        return Ok(Value::Int(0));
+    }
+
+    (n - k + 1..=n)
+        .try_fold(1i64, |product, i| product.checked_mul(i))
+        .map(Value::Int)
+        .ok_or(RuntimeError::IntegerOverflow)
+}
+
+/// The number of ways to choose `k` out of `n` items, ignoring the order.
+pub fn comb(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [n, k] = ints(scope, arguments, input)?;
+
+    if n < 0 || k < 0 {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    if k > n {
+        return Ok(Value::Int(0));
+    }
+
+    let k = k.min(n - k);
+    let mut result = 1i128;
+
+    // Every intermediate result is itself a binomial coefficient, so the division is exact.
+    for i in 0..k {
+        result = result
+            .checked_mul((n - i) as i128)
+            .ok_or(RuntimeError::IntegerOverflow)?
+            / (i + 1) as i128;
+    }
+
+    i64::try_from(result).map(Value::Int).map_err(|_| RuntimeError::IntegerOverflow)
+}
+
+/// Formats an integer in hex, like `0xff`.
+pub fn hex(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    format_radix(scope, arguments, input, |int| format!("{:#x}", int))
+}
+
+/// Formats an integer in binary, like `0b1010`.
+pub fn bin(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    format_radix(scope, arguments, input, |int| format!("{:#b}", int))
+}
+
+/// Formats an integer in octal, like `0o755`.
+pub fn oct(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    format_radix(scope, arguments, input, |int| format!("{:#o}", int))
+}
+
+/// Formats the integer argument, or the input if there is no argument, with a sign
+/// in front of the prefix for negative integers. Whole floats are accepted as well.
+fn format_radix(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value,
+    format: fn(u64) -> String
+) -> Result<Value, RuntimeError> {
+    let int = match values(scope, arguments, input)? {
+        [Value::Int(int)] => int,
+        [Value::Number(num)] if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => num as i64,
+        _ => return Err(RuntimeError::TypeError),
+    };
+
+    let sign = if int < 0 { "-" } else { "" };
+
+    Ok(Value::String(format!("{}{}", sign, format(int.unsigned_abs()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{new_root_scope, run_input};
+
+    fn run(input: &str) -> Result<Value, RuntimeError> {
+        run_input(&mut new_root_scope(), input, Value::Nil)
+    }
+
+    #[test]
+    pub fn test_integer_functions() {
+        assert_eq!(run("math.gcd(12, 18)").ok(), Some(Value::Int(6)));
+        assert_eq!(run("math.gcd(-4, 0)").ok(), Some(Value::Int(4)));
+        assert_eq!(run("math.lcm(4, 6)").ok(), Some(Value::Int(12)));
+        assert_eq!(run("math.lcm(-3, 0)").ok(), Some(Value::Int(0)));
+        assert!(matches!(run("math.lcm(9223372036854775807, 2)"), Err(RuntimeError::IntegerOverflow)));
+
+        assert_eq!(run("math.factorial(0)").ok(), Some(Value::Int(1)));
+        assert_eq!(run("math.factorial 20").ok(), Some(Value::Int(2_432_902_008_176_640_000)));
+        assert!(matches!(run("math.factorial(21)"), Err(RuntimeError::IntegerOverflow)));
+        assert!(matches!(run("math.factorial(-1)"), Err(RuntimeError::InvalidArgument)));
+        assert!(matches!(run("math.factorial(1.5)"), Err(RuntimeError::TypeError)));
+
+        assert_eq!(run("math.perm(5, 2)").ok(), Some(Value::Int(20)));
+        assert_eq!(run("math.comb(5, 2)").ok(), Some(Value::Int(10)));
+        assert_eq!(run("math.comb(2, 5)").ok(), Some(Value::Int(0)));
+        assert_eq!(run("math.comb(62, 31)").ok(), Some(Value::Int(465_428_353_255_261_088)));
+        assert!(matches!(run("math.comb(100, 50)"), Err(RuntimeError::IntegerOverflow)));
+    }
+
+    #[test]
+    pub fn test_rounding_and_extrema() {
+        assert_eq!(run("math.round(2.5)").ok(), Some(Value::Int(3)));
+        assert_eq!(run("math.round(-2.5)").ok(), Some(Value::Int(-3)));
+        assert_eq!(run("math.floor(-1.5)").ok(), Some(Value::Int(-2)));
+        assert_eq!(run("math.ceil(1.2)").ok(), Some(Value::Int(2)));
+        assert_eq!(run("math.floor(7)").ok(), Some(Value::Int(7)));
+        assert_eq!(run("math.round(1e300)").ok(), Some(Value::Number(1e300)));
+        assert_eq!(run("math.abs(-3)").ok(), Some(Value::Int(3)));
+
+        assert_eq!(run("math.min(3, 1.5, 2)").ok(), Some(Value::Number(1.5)));
+        assert_eq!(run("math.max(3, 1.5, 2)").ok(), Some(Value::Int(3)));
+        assert!(matches!(run("math.max()"), Err(RuntimeError::InvalidArgument)));
+
+        assert_eq!(run("math.sqrt(16)").ok(), Some(Value::Number(4.0)));
+        assert_eq!(run("math.log(8, 2)").ok(), Some(Value::Number(3.0)));
+        assert_eq!(run("math.hex(-255)").ok(), Some(Value::String("-0xff".to_string())));
+    }
+
+    #[test]
+    pub fn test_namespace() {
+        // Dotted names are builtins in pipelines too, and only the prefixed names exist.
+        assert_eq!(run("math.sqrt 16").ok(), Some(Value::Number(4.0)));
+        assert!(matches!(run("sqrt 16"), Err(RuntimeError::CommandNotFound)));
+        assert!(!new_root_scope().contains_key("exp"));
+
+        // The formatters are available without the prefix.
+        assert_eq!(run("hex(255)").ok(), Some(Value::String("0xff".to_string())));
+        assert_eq!(run("0o755 | oct").ok(), Some(Value::String("0o755".to_string())));
+        assert_eq!(run("bin 10").ok(), Some(Value::String("0b1010".to_string())));
+
+        assert!(is_pure("math.sqrt"));
+        assert!(!is_pure("sqrt"));
+        assert!(is_pure("hex") && is_pure("math.hex"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+mod fs;
+mod table;
+mod text;
+mod formats;
+mod math;
+mod settings;
+mod explain;
+
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::{eval, Builtin, RuntimeError, Scope, Table, Value, Variable};
+
+pub use settings::change as change_setting;
+
+/// Registers all builtin functions in the given scope.
+pub fn register(scope: &mut Scope) {
+    insert(scope, "ls", fs::ls);
+
+    insert(scope, "where", table::r#where);
+    insert(scope, "sort-by", table::sort_by);
+    insert(scope, "select", table::select);
+    insert(scope, "first", table::first);
+    insert(scope, "last", table::last);
+    insert(scope, "length", table::length);
+    insert(scope, "group-by", table::group_by);
+    insert(scope, "uniq", table::uniq);
+
+    insert(scope, "lines", text::lines);
+
+    insert(scope, "open", formats::open);
+    insert(scope, "from", formats::from);
+    insert(scope, "to", formats::to);
+
+    insert(scope, "explain", explain::explain);
+
+    insert(scope, "set", settings::set);
+    settings::register(scope);
+
+    math::register(scope);
+}
+
+/// Returns `true` if the builtin with the given name has no side effects.
+pub fn is_pure(name: &str) -> bool {
+    math::is_pure(name)
+}
+
+#[inline]
+fn insert(scope: &mut Scope, name: &str, function: Builtin) {
+    scope.insert(name.to_string(), Variable {
+        mutable: false,
+        value: Value::Function(function),
+    });
+}
+
+/// Returns the input as a table or fails with [RuntimeError::TypeError].
+#[inline]
+fn expect_table(input: Value) -> Result<Table, RuntimeError> {
+    match input {
+        Value::Table(table) => Ok(table),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Evaluates an argument that names a column, a flag or an option.
+fn column_argument(scope: &mut Scope, argument: &Span<Expression>) -> Result<String, RuntimeError> {
+    match eval(scope, argument)? {
+        Value::String(s) => Ok(s),
+        _ => Err(RuntimeError::InvalidArgument),
+    }
+}
+
+/// Evaluates an optional count argument, which defaults to one.
+fn count_argument(scope: &mut Scope, arguments: &[Span<Expression>]) -> Result<usize, RuntimeError> {
+    match arguments {
+        [] => Ok(1),
+        [argument] => match eval(scope, argument)? {
+            Value::Int(n) => usize::try_from(n).map_err(|_| RuntimeError::InvalidArgument),
+            _ => Err(RuntimeError::InvalidArgument),
+        },
+        _ => Err(RuntimeError::InvalidArgument),
+    }
+}
\ No newline at end of file
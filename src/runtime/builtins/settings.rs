@@ -0,0 +1,82 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::builtins::column_argument;
+use crate::runtime::{RuntimeError, Scope, Value, Variable};
+
+/// The settings of the shell and the values they can have, the first one is the default.
+/// Settings are mutable variables, so they can be read like any other variable.
+const SETTINGS: &[(&str, &[&str])] = &[
+    ("edit-mode", &["emacs", "vi"]),
+    ("auto-pairs", &["off", "on"]),
+    ("capture-stderr", &["off", "on"]),
+];
+
+/// The settings that can be any text, with their defaults. These are the templates for
+/// the prompt, see [crate::prompt], and the model for commands, see [crate::assistant].
+const TEXT_SETTINGS: &[(&str, &str)] = &[
+    ("prompt", "{cwd}{status}> "),
+    ("right-prompt", "{duration}"),
+    ("transient-prompt", "> "),
+    ("ollama-url", "http://127.0.0.1:11434"),
+    ("ollama-model", "llama3.2"),
+];
+
+/// Inserts the settings with their default values.
+pub fn register(scope: &mut Scope) {
+    for (name, values) in SETTINGS {
+        scope.insert(name.to_string(), Variable {
+            mutable: true,
+            value: Value::String(values[0].to_string()),
+        });
+    }
+
+    for (name, text) in TEXT_SETTINGS {
+        scope.insert(name.to_string(), Variable {
+            mutable: true,
+            value: Value::String(text.to_string()),
+        });
+    }
+}
+
+/// Changes a setting, like `set edit-mode vi` or `set prompt "{user}@{host} {cwd}> "`.
+/// Without arguments, returns all settings.
+pub fn set(scope: &mut Scope, arguments: &[Span<Expression>], _: Value) -> Result<Value, RuntimeError> {
+    let [name, value] = arguments else {
+        if !arguments.is_empty() {
+            return Err(RuntimeError::InvalidArgument);
+        }
+
+        return Ok(Value::Record(
+            SETTINGS
+                .iter()
+                .map(|(name, _)| *name)
+                .chain(TEXT_SETTINGS.iter().map(|(name, _)| *name))
+                .filter_map(|name| Some((name.to_string(), scope.get(name)?.value.clone())))
+                .collect()
+        ));
+    };
+
+    let name = column_argument(scope, name)?;
+    let value = column_argument(scope, value)?;
+
+    match change(scope, &name, value) {
+        true => Ok(Value::Nil),
+        false => Err(RuntimeError::InvalidArgument),
+    }
+}
+
+/// Changes a setting if the value is valid for it, and returns whether it did.
+pub fn change(scope: &mut Scope, name: &str, value: String) -> bool {
+    let valid = SETTINGS
+        .iter()
+        .any(|(setting, values)| *setting == name && values.contains(&value.as_str()))
+        || TEXT_SETTINGS.iter().any(|(setting, _)| *setting == name);
+
+    match scope.get_mut(name) {
+        Some(variable) if valid && variable.mutable => {
+            variable.value = Value::String(value);
+            true
+        }
+        _ => false,
+    }
+}
\ No newline at end of file
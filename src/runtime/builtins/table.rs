@@ -0,0 +1,346 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::builtins::{column_argument, count_argument, expect_table};
+use crate::runtime::{apply_binary, apply_conversion, apply_unary, eval, RuntimeError, Scope, Table, Value, Variable};
+use std::cmp::Ordering;
+
+/// Keeps the rows for which the predicate is true. Bare words in the predicate
+/// that name a column evaluate to the value of that column in the current row.
+pub fn r#where(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [predicate] = arguments else {
+        return Err(RuntimeError::InvalidArgument);
+    };
+
+    let mut table = expect_table(input)?;
+    let mut rows = Vec::with_capacity(table.rows.len());
+
+    for row in table.rows {
+        match eval_row(scope, predicate, &table.columns, &row)? {
+            Value::Bool(true) => rows.push(row),
+            Value::Bool(false) => {}
+            _ => return Err(RuntimeError::TypeError),
+        }
+    }
+
+    table.rows = rows;
+    Ok(Value::Table(table))
+}
+
+/// Evaluates the expression for a row, with the names of columns evaluating to their
+/// values. Columns are never run as programs, also in the arguments of builtins.
+fn eval_row(
+    scope: &mut Scope,
+    expression: &Span<Expression>,
+    columns: &[String],
+    row: &[Value]
+) -> Result<Value, RuntimeError> {
+    let column = |name: &str| columns
+        .iter()
+        .position(|column| column == name)
+        .map(|i| row[i].clone());
+
+    match &expression.value {
+        Expression::Binary { left, operation, right } => {
+            let left = eval_row(scope, left, columns, row)?;
+            let right = eval_row(scope, right, columns, row)?;
+
+            apply_binary(operation, left, right)
+        }
+        Expression::Unary { operation, operand } => apply_unary(operation, eval_row(scope, operand, columns, row)?),
+        Expression::Conversion { operand, unit } => apply_conversion(eval_row(scope, operand, columns, row)?, unit),
+        Expression::Numeral { value, .. } => eval_row(scope, value, columns, row),
+        Expression::Word(name) => Ok(column(name).unwrap_or_else(|| Value::String(name.to_string()))),
+        Expression::CommandInvocation { name, arguments } => match column(name) {
+            Some(value) if arguments.is_empty() => Ok(value),
+            // A column isn't a program, so it takes no arguments.
+            Some(_) => Err(RuntimeError::InvalidArgument),
+            None => with_columns(scope, columns, row, |scope| eval(scope, expression)),
+        },
+        _ => with_columns(scope, columns, row, |scope| eval(scope, expression)),
+    }
+}
+
+/// Runs `f` with the columns of the row as variables, so that the arguments of builtins
+/// can name them. The variables they shadow are restored afterwards.
+fn with_columns<T>(scope: &mut Scope, columns: &[String], row: &[Value], f: impl FnOnce(&mut Scope) -> T) -> T {
+    let shadowed = columns
+        .iter()
+        .zip(row)
+        .map(|(name, value)| {
+            let variable = Variable { mutable: false, value: value.clone() };
+            (name, scope.insert(name.clone(), variable))
+        })
+        .collect::<Vec<_>>();
+
+    let result = f(scope);
+
+    for (name, variable) in shadowed.into_iter().rev() {
+        match variable {
+            Some(variable) => scope.insert(name.clone(), variable),
+            None => scope.remove(name),
+        };
+    }
+
+    result
+}
+
+/// Sorts the rows by the values of a column. Pass `-r` (`--reverse`) to sort descending.
+pub fn sort_by(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let mut table = expect_table(input)?;
+    let mut column = None;
+    let mut reverse = false;
+
+    for argument in arguments {
+        match column_argument(scope, argument)?.as_str() {
+            "-r" | "--reverse" => reverse = true,
+            name if column.is_none() => column = Some(
+                table.column_index(name).ok_or(RuntimeError::InvalidArgument)?
+            ),
+            _ => return Err(RuntimeError::InvalidArgument),
+        }
+    }
+
+    let column = column.ok_or(RuntimeError::InvalidArgument)?;
+
+    table.rows.sort_by(|a, b| {
+        let ordering = a[column].compare(&b[column]).unwrap_or(Ordering::Equal);
+
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    Ok(Value::Table(table))
+}
+
+/// Keeps only the given columns, in the given order.
+pub fn select(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let table = expect_table(input)?;
+
+    let indices = arguments
+        .iter()
+        .map(|argument| table
+            .column_index(&column_argument(scope, argument)?)
+            .ok_or(RuntimeError::InvalidArgument))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut selected = Table::new(indices.iter().map(|&i| table.columns[i].clone()).collect());
+
+    selected.rows = table.rows
+        .into_iter()
+        .map(|row| indices.iter().map(|&i| row[i].clone()).collect())
+        .collect();
+
+    Ok(Value::Table(selected))
+}
+
+/// Keeps the first `n` rows, one by default.
+pub fn first(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let n = count_argument(scope, arguments)?;
+    let mut table = expect_table(input)?;
+
+    table.rows.truncate(n);
+    Ok(Value::Table(table))
+}
+
+/// Keeps the last `n` rows, one by default.
+pub fn last(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let n = count_argument(scope, arguments)?;
+    let mut table = expect_table(input)?;
+
+    let skip = table.rows.len().saturating_sub(n);
+    table.rows.drain(..skip);
+    Ok(Value::Table(table))
+}
+
+/// Returns the number of rows of a table, items of a list or chars of a string.
+pub fn length(_: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    match input {
+        Value::Table(table) => Ok(Value::Int(table.rows.len() as i64)),
+        Value::List(list) => Ok(Value::Int(list.len() as i64)),
+        Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Groups the rows by the values of a column. The result has one row per distinct
+/// value, in order of first appearance, with the count and the grouped rows as a table.
+pub fn group_by(scope: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    let [argument] = arguments else {
+        return Err(RuntimeError::InvalidArgument);
+    };
+
+    let table = expect_table(input)?;
+    let name = column_argument(scope, argument)?;
+    let column = table.column_index(&name).ok_or(RuntimeError::InvalidArgument)?;
+
+    let mut groups = Vec::<(Value, Table)>::new();
+
+    for row in table.rows {
+        let group = match groups.iter_mut().find(|(key, _)| *key == row[column]) {
+            Some((_, group)) => group,
+            None => {
+                groups.push((row[column].clone(), Table::new(table.columns.clone())));
+                &mut groups.last_mut().unwrap().1
+            }
+        };
+
+        group.rows.push(row);
+    }
+
+    let mut grouped = Table::new(vec![name, "count".to_string(), "items".to_string()]);
+
+    grouped.rows = groups
+        .into_iter()
+        .map(|(key, group)| vec![
+            key,
+            Value::Int(group.rows.len() as i64),
+            Value::Table(group),
+        ])
+        .collect();
+
+    Ok(Value::Table(grouped))
+}
+
+/// Removes duplicate rows, keeping the first occurrence.
+pub fn uniq(_: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    let mut table = expect_table(input)?;
+    let mut rows = Vec::<Vec<Value>>::with_capacity(table.rows.len());
+
+    for row in table.rows {
+        if !rows.contains(&row) {
+            rows.push(row);
+        }
+    }
+
+    table.rows = rows;
+    Ok(Value::Table(table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::ast::BinaryOperation;
+    use crate::runtime::{new_root_scope, run_input};
+    use crate::units::Quantity;
+
+    fn files() -> Table {
+        let mut table = Table::new(vec!["name".to_string(), "type".to_string(), "size".to_string()]);
+
+        for (name, kind, size) in [("b.rs", "file", 30), ("src", "dir", 0), ("a.rs", "file", 10), ("c.md", "file", 20)] {
+            table.rows.push(vec![Value::String(name.to_string()), Value::String(kind.to_string()), Value::Int(size)]);
+        }
+
+        table
+    }
+
+    /// Runs a pipeline stage like `where size > 10` with the table as its input.
+    fn run(input: &str, table: Table) -> Result<Value, RuntimeError> {
+        run_input(&mut new_root_scope(), input, Value::Table(table))
+    }
+
+    /// Returns the values of the column of the table that the stage returns.
+    fn column(input: &str, name: &str) -> Vec<Value> {
+        match run(input, files()) {
+            Ok(Value::Table(table)) => {
+                let index = table.column_index(name).unwrap();
+                table.rows.into_iter().map(|row| row[index].clone()).collect()
+            }
+            result => panic!("expected a table from {:?}, got {:?}", input, result),
+        }
+    }
+
+    fn strings(strings: &[&str]) -> Vec<Value> {
+        strings.iter().map(|s| Value::String(s.to_string())).collect()
+    }
+
+    #[test]
+    pub fn test_where() {
+        assert_eq!(column("where size > 10", "name"), strings(&["b.rs", "c.md"]));
+        assert_eq!(column("where size <= 10", "name"), strings(&["src", "a.rs"]));
+        assert_eq!(column("where type == dir", "name"), strings(&["src"]));
+        assert_eq!(column("where type != dir && size < 30", "name"), strings(&["a.rs", "c.md"]));
+        assert!(run("where size", files()).is_err());
+        assert!(run("where size > 1", Table::new(Vec::new())).is_ok());
+
+        // Columns are found in any expression, and never run as programs.
+        assert_eq!(column("where (!(size > 10))", "name"), strings(&["src", "a.rs"]));
+        assert_eq!(column("where (-size < -15)", "name"), strings(&["b.rs", "c.md"]));
+        assert_eq!(column("where (math.max(size, 15) > 15)", "name"), strings(&["b.rs", "c.md"]));
+        assert!(matches!(run("where (size to kB > 1kB)", files()), Err(RuntimeError::InvalidArgument)));
+
+        let mut sizes = Table::new(vec!["size".to_string()]);
+        sizes.rows.push(vec![Value::Quantity(Quantity::size(2000))]);
+        sizes.rows.push(vec![Value::Quantity(Quantity::size(500))]);
+
+        match run("where ((size) to kB > 1kB)", sizes) {
+            Ok(Value::Table(table)) => assert_eq!(table.rows, [[Value::Quantity(Quantity::size(2000))]]),
+            result => panic!("expected a table, got {:?}", result),
+        }
+    }
+
+    #[test]
+    pub fn test_sort_and_slice() {
+        assert_eq!(column("sort-by name", "name"), strings(&["a.rs", "b.rs", "c.md", "src"]));
+        assert_eq!(column("sort-by size -r", "name"), strings(&["b.rs", "c.md", "a.rs", "src"]));
+        assert!(run("sort-by missing", files()).is_err());
+
+        assert_eq!(column("first", "name"), strings(&["b.rs"]));
+        assert_eq!(column("first 2", "name"), strings(&["b.rs", "src"]));
+        assert_eq!(column("last 2", "name"), strings(&["a.rs", "c.md"]));
+        assert_eq!(column("last 10", "name").len(), 4);
+
+        match run("select size name", files()) {
+            Ok(Value::Table(table)) => {
+                assert_eq!(table.columns, ["size", "name"]);
+                assert_eq!(table.rows[0], [Value::Int(30), Value::String("b.rs".to_string())]);
+            }
+            result => panic!("expected a table, got {:?}", result),
+        }
+
+        assert!(run("select missing", files()).is_err());
+    }
+
+    #[test]
+    pub fn test_length_group_and_uniq() {
+        assert_eq!(run("length", files()).ok(), Some(Value::Int(4)));
+        assert_eq!(
+            run_input(&mut new_root_scope(), "length", Value::String("héllo".to_string())).ok(),
+            Some(Value::Int(5))
+        );
+
+        assert_eq!(column("group-by type", "type"), strings(&["file", "dir"]));
+        assert_eq!(column("group-by type", "count"), [Value::Int(3), Value::Int(1)]);
+
+        let mut duplicated = files();
+        duplicated.rows.extend(files().rows);
+        duplicated.rows.push(files().rows[2].clone());
+
+        match run("uniq", duplicated) {
+            Ok(Value::Table(table)) => assert_eq!(table, files()),
+            result => panic!("expected a table, got {:?}", result),
+        }
+    }
+
+    #[test]
+    pub fn test_comparisons() {
+        let compare = |operation, left, right| apply_binary(&operation, left, right).ok();
+        let string = |s: &str| Value::String(s.to_string());
+
+        assert_eq!(compare(BinaryOperation::LessThan, Value::Int(1), Value::Number(1.5)), Some(Value::Bool(true)));
+        assert_eq!(compare(BinaryOperation::GreaterThanOrEqual, Value::Int(2), Value::Int(2)), Some(Value::Bool(true)));
+        assert_eq!(compare(BinaryOperation::LessThanOrEqual, string("b"), string("a")), Some(Value::Bool(false)));
+        assert_eq!(compare(BinaryOperation::NotEqual, string("a"), Value::Int(1)), Some(Value::Bool(true)));
+        assert_eq!(compare(BinaryOperation::GreaterThan, string("a"), Value::Int(1)), None);
+    }
+}
\ No newline at end of file
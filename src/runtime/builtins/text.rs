@@ -0,0 +1,17 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::{RuntimeError, Scope, Value};
+
+/// Splits a string into a list of its lines.
+pub fn lines(_: &mut Scope, arguments: &[Span<Expression>], input: Value) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::InvalidArgument);
+    }
+
+    match input {
+        Value::String(s) => Ok(Value::List(
+            s.lines().map(|line| Value::String(line.to_string())).collect()
+        )),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
\ No newline at end of file
@@ -1,63 +1,398 @@
-use std::io::stdout;
-use std::process::Command;
-use crate::read::ast::{BinaryOperation, Expression};
+use std::cmp::Ordering;
+use crate::read::ast::{BinaryOperation, Expression, UnaryOperation};
 use crate::read::bytes::Span;
 use crate::runtime::scope::Scope;
-use crate::runtime::{RuntimeError, Value, Variable};
-use crate::runtime::resolve::search_program_in_path;
+use crate::runtime::{builtins, run_pipeline, RuntimeError, Value, Variable};
+use crate::units::{Quantity, Unit};
 
 pub fn eval(
     scope: &mut Scope,
     root_expression: &Span<Expression>
 ) -> Result<Value, RuntimeError> {
-    match &root_expression.value {
+    eval_with_input(scope, root_expression, Value::Nil)
+}
+
+/// Evaluates an expression that is a stage of a pipeline. If the expression
+/// invokes a builtin, the input is passed to it.
+///
+/// Errors are located at the innermost expression that caused them, see [RuntimeError::at].
+pub fn eval_with_input(
+    scope: &mut Scope,
+    root_expression: &Span<Expression>,
+    input: Value
+) -> Result<Value, RuntimeError> {
+    let result = match &root_expression.value {
         Expression::Binary { left, operation, right } => {
-            let left = eval(scope, left)?;
-            let right = eval(scope, right)?;
-
-            match operation {
-                BinaryOperation::Add => Ok(Value::Number(
-                    match (left, right) {
-                        (Value::Number(left), Value::Number(right)) => left + right,
-                        _ => return Err(RuntimeError::UnimplementedError),
-                    }
-                )),
-                BinaryOperation::Multiply => Ok(Value::Number(
-                    match (left, right) {
-                        (Value::Number(left), Value::Number(right)) => left * right,
-                        _ => return Err(RuntimeError::UnimplementedError)
-                    }
-                )),
-                BinaryOperation::Divide => Ok(Value::Number(
-                    match (left, right) {
-                        (Value::Number(left), Value::Number(right)) => left / right,
-                        _ => return Err(RuntimeError::UnimplementedError)
-                    }
-                )),
-                _ => Err(RuntimeError::UnimplementedFeature),
-            }
+            let left_value = eval(scope, left)?;
+            let right_value = eval(scope, right)?;
+            let kinds = (left_value.kind(), right_value.kind());
+
+            apply_binary(operation, left_value, right_value).map_err(|error| match error {
+                // Point out the operands if their kinds don't fit.
+                RuntimeError::TypeError | RuntimeError::IncompatibleUnits => RuntimeError::Located {
+                    error: Box::new(error),
+                    range: root_expression.range.clone(),
+                    labels: vec![
+                        (left.range.clone(), format!("this is {}", kinds.0)),
+                        (right.range.clone(), format!("this is {}", kinds.1)),
+                    ],
+                },
+                error => error,
+            })
         }
-        Expression::CommandInvocation(command) => {
-            match Command::new(command)
-                .stdout(stdout())
-                .output() {
-                Ok(output) => {
-                    if output.status.success() {
-                        Ok(Value::Nil)
-                    } else {
-                        Ok(Value::Number(output.status.code().unwrap_or(0) as f64))
-                    }
-                }
-                Err(_) => {
-                    match scope.get(*command) {
-                        Some(Variable { value: Value::Function(f), .. }) => f(scope),
-                        Some(Variable { value: Value::String(s), .. }) => Ok(Value::String(s.clone())),
-                        _ => Err(RuntimeError::UndefinedVariable),
-                    }
-                }
+        Expression::Unary { operation, operand } => {
+            let operand = eval(scope, operand)?;
+
+            apply_unary(operation, operand)
+        }
+        Expression::Conversion { operand, unit } => {
+            let value = eval(scope, operand)?;
+            let kind = value.kind();
+
+            apply_conversion(value, unit).map_err(|error| match error {
+                RuntimeError::IncompatibleUnits => RuntimeError::Located {
+                    error: Box::new(error),
+                    range: root_expression.range.clone(),
+                    labels: vec![(operand.range.clone(), format!("this is {}", kind))],
+                },
+                error => error,
+            })
+        }
+        Expression::Pipeline(stages) => run_pipeline(scope, stages, input),
+        Expression::CommandInvocation { name, arguments } => {
+            match lookup(scope, name) {
+                Some(Value::Function(f)) => f(scope, arguments, input),
+                Some(value) if arguments.is_empty() => Ok(value.clone()),
+                _ => run_pipeline(scope, std::slice::from_ref(root_expression), input),
             }
         }
         Expression::Number(num) => Ok(Value::Number(*num)),
-        Expression::String(s) => Ok(Value::String(s.to_string())),
+        Expression::Integer(int) => Ok(Value::Int(*int)),
+        Expression::Quantity(amount, unit) => Ok(Value::Quantity(Quantity::new(*amount, unit))),
+        Expression::Numeral { value, .. } => eval(scope, value),
+        Expression::String(s) | Expression::Word(s) => Ok(Value::String(s.to_string())),
+        // Only trees from `parse_recovering` have errors, and they aren't meant to be run.
+        Expression::Error => Err(RuntimeError::InvalidSyntax),
+    };
+
+    result.map_err(|error| error.at(root_expression.range.clone()))
+}
+
+/// Looks up a variable by name. Dotted names like `math.sqrt` look up the
+/// fields of records.
+pub fn lookup<'s>(scope: &'s Scope, name: &str) -> Option<&'s Value> {
+    if let Some(Variable { value, .. }) = scope.get(name) {
+        return Some(value);
+    }
+
+    let mut path = name.split('.');
+    let mut value = &scope.get(path.next()?)?.value;
+
+    for field in path {
+        value = match value {
+            Value::Record(fields) => fields
+                .iter()
+                .find_map(|(name, value)| (name == field).then_some(value))?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// Returns `true` if invoking the command has no side effects. Reading a variable
+/// is pure, and so are the math functions, but not other builtins or programs.
+pub fn is_pure_command(scope: &Scope, name: &str, arguments: &[Span<Expression>]) -> bool {
+    match lookup(scope, name) {
+        Some(Value::Function(_)) => builtins::is_pure(name),
+        Some(_) => arguments.is_empty(),
+        None => false,
+    }
+}
+
+/// Applies a unary operation to an evaluated operand.
+pub fn apply_unary(operation: &UnaryOperation, operand: Value) -> Result<Value, RuntimeError> {
+    match (operation, operand) {
+        (UnaryOperation::Negate, Value::Int(int)) => int
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or(RuntimeError::IntegerOverflow),
+        (UnaryOperation::Negate, Value::Number(num)) => Ok(Value::Number(-num)),
+        (UnaryOperation::Negate, Value::Quantity(quantity)) => Ok(Value::Quantity(
+            Quantity::new(-quantity.amount, quantity.unit)
+        )),
+        (UnaryOperation::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (UnaryOperation::Not, Value::Int(int)) => Ok(Value::Int(!int)),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Converts a quantity into the unit, which needs to have the same dimension.
+pub fn apply_conversion(value: Value, unit: &'static Unit) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Quantity(quantity) => quantity.to(unit).map(Value::Quantity).ok_or(RuntimeError::IncompatibleUnits),
+        _ => Err(RuntimeError::TypeError),
+    }
+}
+
+/// Applies a binary operation to two evaluated operands.
+///
+/// Arithmetic on two integers gives an integer and fails with
+/// [RuntimeError::IntegerOverflow] instead of wrapping. As soon as one operand is a
+/// float, both are converted to floats. Division always gives a float, and so does
+/// exponentiation with a negative integer exponent. Bitwise operations and shifts
+/// only accept integers. See [apply_quantity] for quantities.
+pub fn apply_binary(
+    operation: &BinaryOperation,
+    left: Value,
+    right: Value
+) -> Result<Value, RuntimeError> {
+    macro_rules! compare {
+        ($($ordering: pat),+) => {
+            match left.compare(&right) {
+                Some(ordering) => Ok(Value::Bool(matches!(ordering, $($ordering)|+))),
+                None => Err(RuntimeError::TypeError),
+            }
+        };
+    }
+
+    macro_rules! arithmetic {
+        ($checked: ident, $float: expr) => {
+            match (&left, &right) {
+                (Value::Int(left), Value::Int(right)) => left
+                    .$checked(*right)
+                    .map(Value::Int)
+                    .ok_or(RuntimeError::IntegerOverflow),
+                _ => match (left.as_float(), right.as_float()) {
+                    (Some(left), Some(right)) => Ok(Value::Number($float(left, right))),
+                    _ => Err(RuntimeError::TypeError),
+                },
+            }
+        };
+    }
+
+    macro_rules! integer {
+        ($apply: expr) => {
+            match (left, right) {
+                (Value::Int(left), Value::Int(right)) => $apply(left, right).map(Value::Int),
+                _ => Err(RuntimeError::TypeError),
+            }
+        };
+    }
+
+    /// Converts a shift amount, which has to be in `0..64`.
+    fn shift(amount: i64) -> Result<u32, RuntimeError> {
+        match u32::try_from(amount) {
+            Ok(amount) if amount < i64::BITS => Ok(amount),
+            _ => Err(RuntimeError::IntegerOverflow),
+        }
+    }
+
+    let quantities = matches!(left, Value::Quantity(_)) || matches!(right, Value::Quantity(_));
+
+    // Quantities only compare with quantities of the same dimension, a bare number has
+    // no unit to compare in.
+    let incomparable = match (&left, &right) {
+        (Value::Quantity(left), Value::Quantity(right)) => left.unit.dimension != right.unit.dimension,
+        (Value::Quantity(_), Value::Int(_) | Value::Number(_))
+            | (Value::Int(_) | Value::Number(_), Value::Quantity(_)) => true,
+        _ => false,
+    };
+
+    match operation {
+        BinaryOperation::Add
+            | BinaryOperation::Subtract
+            | BinaryOperation::Multiply
+            | BinaryOperation::Divide if quantities => apply_quantity(operation, &left, &right),
+        BinaryOperation::Add => arithmetic!(checked_add, |left, right| left + right),
+        BinaryOperation::Subtract => arithmetic!(checked_sub, |left, right| left - right),
+        BinaryOperation::Multiply => arithmetic!(checked_mul, |left, right| left * right),
+        BinaryOperation::Divide => match (left.as_float(), right.as_float()) {
+            (Some(left), Some(right)) => Ok(Value::Number(left / right)),
+            _ => Err(RuntimeError::TypeError),
+        },
+        BinaryOperation::Modulo => match (&left, &right) {
+            (Value::Int(_), Value::Int(0)) => Err(RuntimeError::DivisionByZero),
+            _ => arithmetic!(checked_rem, |left, right| left % right),
+        },
+        BinaryOperation::Exponentiate => match (&left, &right) {
+            (Value::Int(base), Value::Int(exponent)) if *exponent >= 0 => u32::try_from(*exponent)
+                .ok()
+                .and_then(|exponent| base.checked_pow(exponent))
+                .map(Value::Int)
+                .ok_or(RuntimeError::IntegerOverflow),
+            _ => match (left.as_float(), right.as_float()) {
+                (Some(base), Some(exponent)) => Ok(Value::Number(base.powf(exponent))),
+                _ => Err(RuntimeError::TypeError),
+            },
+        },
+        BinaryOperation::BitwiseAnd => integer!(|left: i64, right: i64| Ok(left & right)),
+        BinaryOperation::BitwiseOr => integer!(|left: i64, right: i64| Ok(left | right)),
+        BinaryOperation::BitwiseXor => integer!(|left: i64, right: i64| Ok(left ^ right)),
+        BinaryOperation::BitwiseShiftLeft => integer!(|left: i64, right| Ok(left << shift(right)?)),
+        BinaryOperation::BitwiseShiftRight => integer!(|left: i64, right| Ok(left >> shift(right)?)),
+        BinaryOperation::Equal
+            | BinaryOperation::NotEqual
+            | BinaryOperation::LessThan
+            | BinaryOperation::LessThanOrEqual
+            | BinaryOperation::GreaterThan
+            | BinaryOperation::GreaterThanOrEqual if incomparable => Err(RuntimeError::IncompatibleUnits),
+        BinaryOperation::Equal => Ok(Value::Bool(equal(&left, &right))),
+        BinaryOperation::NotEqual => Ok(Value::Bool(!equal(&left, &right))),
+        BinaryOperation::LessThan => compare!(Ordering::Less),
+        BinaryOperation::LessThanOrEqual => compare!(Ordering::Less, Ordering::Equal),
+        BinaryOperation::GreaterThan => compare!(Ordering::Greater),
+        BinaryOperation::GreaterThanOrEqual => compare!(Ordering::Greater, Ordering::Equal),
+        BinaryOperation::LogicalAnd | BinaryOperation::LogicalOr => match (left, right) {
+            (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(
+                if let BinaryOperation::LogicalAnd = operation {
+                    left && right
+                } else {
+                    left || right
+                }
+            )),
+            _ => Err(RuntimeError::TypeError),
+        },
+        _ => Err(RuntimeError::UnimplementedFeature),
+    }
+}
+
+/// Applies an arithmetic operation where at least one operand is a quantity.
+///
+/// Quantities of the same dimension can be added and subtracted, the result has the
+/// unit of the left operand. Quantities can be multiplied and divided by numbers, and
+/// dividing two quantities of the same dimension gives their ratio as a number.
+fn apply_quantity(
+    operation: &BinaryOperation,
+    left: &Value,
+    right: &Value
+) -> Result<Value, RuntimeError> {
+    match (operation, left, right) {
+        (BinaryOperation::Add | BinaryOperation::Subtract, Value::Quantity(left), Value::Quantity(right)) => {
+            let right = right.to(left.unit).ok_or(RuntimeError::IncompatibleUnits)?;
+
+            let amount = if let BinaryOperation::Add = operation {
+                left.amount + right.amount
+            } else {
+                left.amount - right.amount
+            };
+
+            Ok(Value::Quantity(Quantity::new(amount, left.unit)))
+        }
+        (BinaryOperation::Multiply, Value::Quantity(quantity), factor)
+            | (BinaryOperation::Multiply, factor, Value::Quantity(quantity)) => match factor.as_float() {
+            Some(factor) => Ok(Value::Quantity(Quantity::new(quantity.amount * factor, quantity.unit))),
+            None => Err(RuntimeError::IncompatibleUnits),
+        },
+        (BinaryOperation::Divide, Value::Quantity(left), Value::Quantity(right)) => {
+            let right = right.to(left.unit).ok_or(RuntimeError::IncompatibleUnits)?;
+
+            Ok(Value::Number(left.amount / right.amount))
+        }
+        (BinaryOperation::Divide, Value::Quantity(quantity), divisor) => match divisor.as_float() {
+            Some(divisor) => Ok(Value::Quantity(Quantity::new(quantity.amount / divisor, quantity.unit))),
+            None => Err(RuntimeError::IncompatibleUnits),
+        },
+        _ => Err(RuntimeError::IncompatibleUnits),
+    }
+}
+
+/// Compares two values for equality, integers and floats by their numeric value
+/// and quantities by their amount in the base unit.
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Int(_), Value::Number(_))
+            | (Value::Number(_), Value::Int(_))
+            | (Value::Quantity(_), _)
+            | (_, Value::Quantity(_)) => {
+            left.compare(right) == Some(Ordering::Equal)
+        }
+        _ => left == right,
+    }
+}
+
+/// Parses and evaluates the input with the value as its input, for tests. Located errors
+/// are unwrapped, so that tests can match the error itself.
+#[cfg(test)]
+pub fn run_input(scope: &mut Scope, input: &str, value: Value) -> Result<Value, RuntimeError> {
+    use crate::read::{buffered::Buffered, bytes::Cursor, lex::Lexer, parse::parse};
+
+    let expression = parse(&mut Buffered::new(Lexer::new(Cursor::new(input)))).unwrap();
+
+    match eval_with_input(scope, &expression, value) {
+        Err(RuntimeError::Located { error, .. }) => Err(*error),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::buffered::Buffered;
+    use crate::read::bytes::Cursor;
+    use crate::read::lex::Lexer;
+    use crate::read::parse::parse_recovering;
+    use crate::runtime::new_root_scope;
+
+    #[test]
+    pub fn test_integer_arithmetic() {
+        let int = |operation, left, right| apply_binary(&operation, Value::Int(left), Value::Int(right));
+
+        assert!(matches!(int(BinaryOperation::Add, 1, 2), Ok(Value::Int(3))));
+        assert!(matches!(int(BinaryOperation::Divide, 7, 2), Ok(Value::Number(3.5))));
+        assert!(matches!(int(BinaryOperation::Exponentiate, 2, -1), Ok(Value::Number(0.5))));
+        assert!(matches!(int(BinaryOperation::BitwiseShiftLeft, 1, 4), Ok(Value::Int(16))));
+        assert!(matches!(int(BinaryOperation::Multiply, i64::MAX, 2), Err(RuntimeError::IntegerOverflow)));
+        assert!(matches!(int(BinaryOperation::Modulo, 1, 0), Err(RuntimeError::DivisionByZero)));
+        assert!(matches!(
+            apply_binary(&BinaryOperation::Add, Value::Int(1), Value::Number(0.5)),
+            Ok(Value::Number(1.5))
+        ));
+        assert!(matches!(
+            apply_binary(&BinaryOperation::Equal, Value::Int(1), Value::Number(1.0)),
+            Ok(Value::Bool(true))
+        ));
+    }
+
+    #[test]
+    pub fn test_precedence() {
+        let run = |input| run_input(&mut new_root_scope(), input, Value::Nil).ok();
+
+        assert_eq!(run("-2 ** 2"), Some(Value::Int(-4)));
+        assert_eq!(run("(-2) ** 2"), Some(Value::Int(4)));
+        assert_eq!(run("2 ** -1"), Some(Value::Number(0.5)));
+        assert_eq!(run("2 ** 3 ** 2"), Some(Value::Int(512)));
+        assert_eq!(run("-2 * 3 + 1"), Some(Value::Int(-5)));
+        assert_eq!(run("!(1 < 2) || 1 < 2"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    pub fn test_eval_error_node() {
+        let (expression, errors) = parse_recovering(&mut Buffered::new(Lexer::new(Cursor::new("1 + $"))));
+        assert_eq!(errors.len(), 1);
+
+        assert!(matches!(
+            eval(&mut new_root_scope(), &expression),
+            Err(RuntimeError::Located { error, range, .. }) if matches!(*error, RuntimeError::InvalidSyntax) && range == (4..5)
+        ));
+    }
+
+    #[test]
+    pub fn test_quantities() {
+        let run = |input| run_input(&mut new_root_scope(), input, Value::Nil);
+
+        // Quantities compare by their amount in the base unit, if they have the same dimension.
+        assert_eq!(run("1KiB > 1000B").ok(), Some(Value::Bool(true)));
+        assert_eq!(run("1KiB == 1024B").ok(), Some(Value::Bool(true)));
+        assert_eq!(run("1.5min != 90s").ok(), Some(Value::Bool(false)));
+
+        // Bare numbers have no unit, so they don't compare with quantities.
+        for input in ["1KiB == 1024", "5km > 10", "30 < 1min", "1KiB < 1s", "1KiB != 1s"] {
+            assert!(matches!(run(input), Err(RuntimeError::IncompatibleUnits)), "{}", input);
+        }
+
+        let hours = run("250ms to h").unwrap();
+        assert_eq!(hours.to_argument(), format!("{}h", 0.25 / 3600.0));
+        assert_eq!(run("10.123MB").ok().map(|value| value.to_argument()), Some("10.123MB".to_string()));
     }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+//! # CSV serialization and parsing of values.
+
+use crate::runtime::formats::FormatError;
+use crate::runtime::{RuntimeError, Table, Value};
+
+/// Parses CSV text with a header row into a table. Fields that are integers or numbers
+/// written the way they would be written back, like `12` but not `012` or `+12`, become
+/// integers or numbers, everything else stays a string.
+pub fn parse(text: &str) -> Result<Value, FormatError> {
+    let mut records = Vec::<(usize, Vec<String>)>::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut record_start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) if chars.peek().map(|&(_, c)| c) == Some('"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some((_, '"')) => break,
+                        Some((_, c)) => field.push(c),
+                        None => return Err(FormatError::new(index, "Unterminated quoted field")),
+                    }
+                }
+
+                match chars.peek() {
+                    None | Some((_, ',' | '\n' | '\r')) => {}
+                    Some(&(index, _)) => return Err(FormatError::new(index, "Expected ',' after quoted field")),
+                }
+            }
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek().map(|&(_, c)| c) == Some('\n') => {}
+            // Blank lines, like the one editors leave at the end, aren't records.
+            '\n' if text[record_start..index].trim_end_matches('\r').is_empty() => record_start = index + 1,
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                records.push((record_start, std::mem::take(&mut fields)));
+                record_start = index + 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_start, fields));
+    }
+
+    let mut records = records.into_iter();
+
+    let Some((_, header)) = records.next() else {
+        return Ok(Value::Table(Table::new(Vec::new())));
+    };
+
+    let mut table = Table::new(header);
+
+    for (offset, record) in records {
+        if record.len() != table.columns.len() {
+            return Err(FormatError::new(offset, "Record has a different number of fields than the header"));
+        }
+
+        table.rows.push(record.into_iter().map(infer).collect());
+    }
+
+    Ok(Value::Table(table))
+}
+
+fn infer(field: String) -> Value {
+    let numeric = !field.is_empty() && field
+        .bytes()
+        .all(|byte| matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'));
+
+    if !numeric {
+        return Value::String(field);
+    }
+
+    let value = match field.parse() {
+        Ok(int) => Value::Int(int),
+        Err(_) => match field.parse() {
+            Ok(num) => Value::Number(num),
+            Err(_) => return Value::String(field),
+        },
+    };
+
+    // Other spellings of a number, like `007` or `+1`, are kept so they are written back
+    // as they were.
+    if value.to_argument() == field {
+        value
+    } else {
+        Value::String(field)
+    }
+}
+
+/// Writes a table as CSV with a header row. A record is written as a table with a single row.
+pub fn write(value: &Value) -> Result<String, RuntimeError> {
+    let mut out = String::new();
+
+    let (columns, rows) = match value {
+        Value::Table(table) => (
+            table.columns.iter().map(String::as_str).collect::<Vec<_>>(),
+            table.rows.iter().map(|row| row.iter().collect()).collect::<Vec<Vec<_>>>(),
+        ),
+        Value::Record(fields) => (
+            fields.iter().map(|(name, _)| name.as_str()).collect(),
+            vec![fields.iter().map(|(_, value)| value).collect()],
+        ),
+        _ => return Err(RuntimeError::TypeError),
+    };
+
+    write_record(columns.into_iter(), &mut out);
+
+    for row in rows {
+        let texts = row
+            .iter()
+            .map(|value| match value {
+                Value::Quantity(quantity) => quantity.base_amount().to_string(),
+                value => value.to_argument(),
+            })
+            .collect::<Vec<_>>();
+        write_record(texts.iter().map(String::as_str), &mut out);
+    }
+
+    Ok(out)
+}
+
+fn write_record<'a>(fields: impl Iterator<Item = &'a str>, out: &mut String) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_csv_round_trip() {
+        let text = "name,size,ratio,code\n\"a, b\",12,1.5,007\n\"say \"\"hi\"\"\",-3,2e-7,+1\n\"two\nlines\",0,,x\n";
+        let value = parse(text).unwrap();
+
+        let Value::Table(table) = &value else {
+            panic!("expected a table, got {:?}", value);
+        };
+
+        assert_eq!(table.columns, ["name", "size", "ratio", "code"]);
+        assert_eq!(table.rows[0], [
+            Value::String("a, b".to_string()),
+            Value::Int(12),
+            Value::Number(1.5),
+            Value::String("007".to_string()),
+        ]);
+        assert_eq!(table.rows[1][0], Value::String("say \"hi\"".to_string()));
+        assert_eq!(table.rows[1][3], Value::String("+1".to_string()));
+        assert_eq!(table.rows[2][2], Value::String(String::new()));
+
+        assert_eq!(write(&value).map_err(|_| ()), Ok(text.to_string()));
+
+        let blank_lines = "name,size\r\n\r\na,1\n\n";
+        assert_eq!(parse(blank_lines), parse("name,size\na,1\n"));
+    }
+
+    #[test]
+    pub fn test_parse_csv_errors() {
+        assert_eq!(parse("a,b\n1\n"), Err(FormatError::new(4, "Record has a different number of fields than the header")));
+        assert_eq!(parse("a\n\"b"), Err(FormatError::new(2, "Unterminated quoted field")));
+    }
+}
\ No newline at end of file
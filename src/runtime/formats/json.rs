@@ -0,0 +1,398 @@
+//! # JSON serialization and parsing of values.
+
+use crate::runtime::formats::{list_or_table, FormatError};
+use crate::runtime::Value;
+use std::fmt::Write;
+
+/// Writes a value as compact JSON. Tables are written as arrays of objects,
+/// quantities as their amount in the base unit, like bytes or seconds, and
+/// functions and non-finite numbers as `null`.
+pub fn write(value: &Value, out: &mut String) {
+    match value {
+        Value::Number(num) if num.is_finite() => {
+            let _ = write!(out, "{}", num);
+        }
+        Value::Int(int) => {
+            let _ = write!(out, "{}", int);
+        }
+        Value::Quantity(quantity) => write(&Value::Number(quantity.base_amount()), out),
+        Value::Number(_) | Value::Nil | Value::Function(_) => out.push_str("null"),
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::String(s) => write_string(s, out),
+        Value::List(list) => {
+            out.push('[');
+
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                write(item, out);
+            }
+
+            out.push(']');
+        }
+        Value::Record(fields) => {
+            out.push('{');
+
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                write_string(name, out);
+                out.push(':');
+                write(value, out);
+            }
+
+            out.push('}');
+        }
+        Value::Table(table) => {
+            out.push('[');
+
+            for (i, row) in table.rows.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                write_row(&table.columns, row, out);
+            }
+
+            out.push(']');
+        }
+    }
+}
+
+/// Writes a row of a table as an object.
+pub fn write_row(columns: &[String], row: &[Value], out: &mut String) {
+    out.push('{');
+
+    for (i, (column, value)) in columns.iter().zip(row).enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        write_string(column, out);
+        out.push(':');
+        write(value, out);
+    }
+
+    out.push('}');
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Parses a JSON document. Objects become records and arrays of objects with
+/// the same keys become tables.
+pub fn parse(text: &str) -> Result<Value, FormatError> {
+    let mut parser = Parser { text, index: 0 };
+
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+
+    if parser.index < text.len() {
+        return Err(FormatError::new(parser.index, "Unexpected trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    index: usize,
+}
+
+impl Parser<'_> {
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.index).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), FormatError> {
+        self.skip_whitespace();
+
+        if self.peek() == Some(byte) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(FormatError::new(self.index, message))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FormatError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => {
+                self.index += 1;
+
+                let mut fields = Vec::new();
+
+                self.skip_whitespace();
+
+                if self.peek() == Some(b'}') {
+                    self.index += 1;
+                    return Ok(Value::Record(fields));
+                }
+
+                loop {
+                    self.skip_whitespace();
+
+                    if self.peek() != Some(b'"') {
+                        return Err(FormatError::new(self.index, "Expected a string as key"));
+                    }
+
+                    let name = self.parse_string()?;
+                    self.expect(b':', "Expected ':' after key")?;
+                    let value = self.parse_value()?;
+
+                    match fields.iter_mut().find(|(existing, _)| *existing == name) {
+                        Some((_, existing)) => *existing = value,
+                        None => fields.push((name, value)),
+                    }
+
+                    self.skip_whitespace();
+
+                    match self.peek() {
+                        Some(b',') => self.index += 1,
+                        Some(b'}') => {
+                            self.index += 1;
+                            return Ok(Value::Record(fields));
+                        }
+                        _ => return Err(FormatError::new(self.index, "Expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.index += 1;
+
+                let mut items = Vec::new();
+
+                self.skip_whitespace();
+
+                if self.peek() == Some(b']') {
+                    self.index += 1;
+                    return Ok(Value::List(items));
+                }
+
+                loop {
+                    items.push(self.parse_value()?);
+
+                    self.skip_whitespace();
+
+                    match self.peek() {
+                        Some(b',') => self.index += 1,
+                        Some(b']') => {
+                            self.index += 1;
+                            return Ok(list_or_table(items));
+                        }
+                        _ => return Err(FormatError::new(self.index, "Expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'"') => Ok(Value::String(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Value::Nil),
+            Some(_) => Err(FormatError::new(self.index, "Expected a value")),
+            None => Err(FormatError::new(self.index, "Unexpected end of input")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, FormatError> {
+        if self.text[self.index..].starts_with(literal) {
+            self.index += literal.len();
+            Ok(value)
+        } else {
+            Err(FormatError::new(self.index, "Expected a value"))
+        }
+    }
+
+    /// Parses a number. Numbers without a fraction or exponent become integers
+    /// if they fit, and floats otherwise.
+    fn parse_number(&mut self) -> Result<Value, FormatError> {
+        let start = self.index;
+
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.index += 1;
+        }
+
+        let text = &self.text[start..self.index];
+
+        if !text.contains(['.', 'e', 'E']) {
+            if let Ok(int) = text.parse() {
+                return Ok(Value::Int(int));
+            }
+        }
+
+        text.parse()
+            .map(Value::Number)
+            .map_err(|_| FormatError::new(start, "Invalid number"))
+    }
+
+    /// Parses a string starting at the opening quote.
+    fn parse_string(&mut self) -> Result<String, FormatError> {
+        self.index += 1;
+
+        let mut string = String::new();
+
+        loop {
+            let start = self.index;
+
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+
+                self.index += 1;
+            }
+
+            string.push_str(&self.text[start..self.index]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.index += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') => {
+                    self.index += 1;
+
+                    let escaped = match self.peek() {
+                        Some(b'u') => {
+                            self.index += 1;
+                            self.parse_unicode_escape()?
+                        }
+                        Some(byte) => {
+                            let escaped = match byte {
+                                b'"' => '"',
+                                b'\\' => '\\',
+                                b'/' => '/',
+                                b'b' => '\u{8}',
+                                b'f' => '\u{c}',
+                                b'n' => '\n',
+                                b'r' => '\r',
+                                b't' => '\t',
+                                _ => return Err(FormatError::new(self.index, "Invalid escape sequence")),
+                            };
+
+                            self.index += 1;
+                            escaped
+                        }
+                        None => return Err(FormatError::new(self.index, "Unterminated string")),
+                    };
+
+                    string.push(escaped);
+                }
+                Some(_) => return Err(FormatError::new(self.index, "Control character in string")),
+                None => return Err(FormatError::new(self.index, "Unterminated string")),
+            }
+        }
+    }
+
+    /// Parses the hex digits of a `\u` escape, including a following low surrogate.
+    fn parse_unicode_escape(&mut self) -> Result<char, FormatError> {
+        let start = self.index;
+        let high = self.parse_hex4()?;
+
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.index..].starts_with("\\u") {
+                return Err(FormatError::new(start, "Unpaired surrogate"));
+            }
+
+            self.index += 2;
+            let low = self.parse_hex4()?;
+
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(FormatError::new(start, "Unpaired surrogate"));
+            }
+
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or(FormatError::new(start, "Invalid unicode escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, FormatError> {
+        let digits = self.text
+            .get(self.index..self.index + 4)
+            .ok_or(FormatError::new(self.index, "Invalid unicode escape"))?;
+
+        let code = u32::from_str_radix(digits, 16)
+            .map_err(|_| FormatError::new(self.index, "Invalid unicode escape"))?;
+
+        self.index += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{RuntimeError, Table};
+
+    #[test]
+    pub fn test_parse_json() {
+        let value = parse(r#"{"a": [1, 2.5e1], "b": "é😀\n", "c": [{"x": true}, {"x": null}]}"#);
+
+        let mut table = Table::new(vec!["x".to_string()]);
+        table.rows = vec![vec![Value::Bool(true)], vec![Value::Nil]];
+
+        assert_eq!(value, Ok(Value::Record(vec![
+            ("a".to_string(), Value::List(vec![Value::Int(1), Value::Number(25.0)])),
+            ("b".to_string(), Value::String("é😀\n".to_string())),
+            ("c".to_string(), Value::Table(table)),
+        ])));
+    }
+
+    #[test]
+    pub fn test_parse_json_error_location() {
+        let text = "{\"a\": 1,\n  \"b\": tru}";
+
+        assert_eq!(
+            parse(text).map_err(|error| error.into_runtime_error(text)).map_err(|error| match error {
+                RuntimeError::ParseError { line, column, .. } => (line, column),
+                _ => (0, 0),
+            }),
+            Err((2, 8))
+        );
+    }
+
+    #[test]
+    pub fn test_write_json() {
+        let mut out = String::new();
+        write(&Value::Record(vec![
+            ("a\"".to_string(), Value::List(vec![Value::Number(1.5), Value::Nil])),
+        ]), &mut out);
+
+        assert_eq!(out, r#"{"a\"":[1.5,null]}"#);
+    }
+}
\ No newline at end of file
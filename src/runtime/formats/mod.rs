@@ -0,0 +1,76 @@
+//! # Conversions between values and text formats.
+
+pub mod json;
+pub mod csv;
+pub mod toml;
+
+use crate::runtime::{RuntimeError, Table, Value};
+
+/// An error while parsing text, located at a byte offset into the text.
+#[derive(Debug, PartialEq)]
+pub struct FormatError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl FormatError {
+    #[inline]
+    pub const fn new(offset: usize, message: &'static str) -> Self {
+        Self { offset, message }
+    }
+
+    /// Converts the error into a [RuntimeError::ParseError] by resolving the
+    /// offset into a line and column of the text.
+    pub fn into_runtime_error(self, text: &str) -> RuntimeError {
+        let before = &text[..self.offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        RuntimeError::ParseError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: self.message,
+        }
+    }
+}
+
+/// Turns a list of records that all have the same fields into a table,
+/// otherwise returns the list as it is.
+pub fn list_or_table(items: Vec<Value>) -> Value {
+    let columns = match items.first() {
+        Some(Value::Record(fields)) => fields
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>(),
+        _ => return Value::List(items),
+    };
+
+    let uniform = items.iter().all(|item| matches!(
+        item,
+        Value::Record(fields) if fields.len() == columns.len()
+            && fields.iter().zip(&columns).all(|((name, _), column)| name == column)
+    ));
+
+    if !uniform {
+        return Value::List(items);
+    }
+
+    let mut table = Table::new(columns);
+
+    table.rows = items
+        .into_iter()
+        .map(|item| match item {
+            Value::Record(fields) => fields.into_iter().map(|(_, value)| value).collect(),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Value::Table(table)
+}
+
+/// Returns the rows of a table as records.
+pub fn table_to_records(table: &Table) -> Vec<Value> {
+    table.rows
+        .iter()
+        .map(|row| Value::Record(table.columns.iter().cloned().zip(row.iter().cloned()).collect()))
+        .collect()
+}
\ No newline at end of file
@@ -0,0 +1,701 @@
+//! # TOML serialization and parsing of values.
+//!
+//! Dates and times are kept as strings, since there is no value for them.
+
+use crate::runtime::formats::{list_or_table, table_to_records, FormatError};
+use crate::runtime::{RuntimeError, Value};
+use std::fmt::Write;
+
+type Fields = Vec<(String, Value)>;
+
+/// Parses a TOML document into a record. Arrays of tables whose entries have
+/// the same keys become tables.
+pub fn parse(text: &str) -> Result<Value, FormatError> {
+    let mut parser = Parser { text, index: 0 };
+    let mut root = Fields::new();
+    let mut current = Vec::<String>::new();
+    // The tables that have a header, which can't be repeated.
+    let mut defined = Vec::<Vec<String>>::new();
+
+    loop {
+        parser.skip_whitespace_and_comments(true);
+
+        let start = parser.index;
+
+        match parser.peek() {
+            None => break,
+            Some(b'[') => {
+                parser.index += 1;
+
+                let array = parser.peek() == Some(b'[');
+
+                if array {
+                    parser.index += 1;
+                }
+
+                let path = parser.parse_key()?;
+
+                parser.skip_whitespace_and_comments(false);
+
+                let closing = if array { "]]" } else { "]" };
+
+                if !parser.text[parser.index..].starts_with(closing) {
+                    return Err(FormatError::new(parser.index, "Expected ']' after table header"));
+                }
+
+                parser.index += closing.len();
+
+                let (last, parents) = path.split_last().unwrap();
+                let parent = table_at(&mut root, parents).map_err(|message| FormatError::new(start, message))?;
+
+                if array {
+                    // The sub-tables of the previous entry of the array can be defined again.
+                    defined.retain(|defined| !(defined.len() > path.len() && defined.starts_with(&path)));
+                } else if defined.contains(&path) {
+                    return Err(FormatError::new(start, "Table is already defined"));
+                } else {
+                    defined.push(path.clone());
+                }
+
+                match parent.iter_mut().find(|(key, _)| key == last) {
+                    Some((_, Value::List(items))) if array => items.push(Value::Record(Fields::new())),
+                    Some((_, Value::Record(_))) if !array => {}
+                    Some(_) => return Err(FormatError::new(start, "Table is already defined")),
+                    None => parent.push((last.clone(), if array {
+                        Value::List(vec![Value::Record(Fields::new())])
+                    } else {
+                        Value::Record(Fields::new())
+                    })),
+                }
+
+                current = path;
+            }
+            Some(_) => {
+                let path = parser.parse_key()?;
+                parser.expect(b'=', "Expected '=' after key")?;
+                let value = parser.parse_value()?;
+
+                let (last, parents) = path.split_last().unwrap();
+                let full_path = current.iter().chain(parents).cloned().collect::<Vec<_>>();
+                let table = table_at(&mut root, &full_path).map_err(|message| FormatError::new(start, message))?;
+
+                if table.iter().any(|(key, _)| key == last) {
+                    return Err(FormatError::new(start, "Key is already defined"));
+                }
+
+                table.push((last.clone(), value));
+            }
+        }
+
+        parser.skip_whitespace_and_comments(false);
+
+        match parser.peek() {
+            None => break,
+            Some(b'\n') => parser.index += 1,
+            Some(b'\r') if parser.text[parser.index..].starts_with("\r\n") => parser.index += 2,
+            Some(_) => return Err(FormatError::new(parser.index, "Expected a new line")),
+        }
+    }
+
+    Ok(normalize(Value::Record(root)))
+}
+
+/// Returns the fields of the table at the path, creating missing tables.
+/// For arrays of tables, the last entry is used.
+fn table_at<'t>(root: &'t mut Fields, path: &[String]) -> Result<&'t mut Fields, &'static str> {
+    let mut table = root;
+
+    for key in path {
+        let index = match table.iter().position(|(existing, _)| existing == key) {
+            Some(index) => index,
+            None => {
+                table.push((key.clone(), Value::Record(Fields::new())));
+                table.len() - 1
+            }
+        };
+
+        table = match &mut table[index].1 {
+            Value::Record(fields) => fields,
+            Value::List(items) => match items.last_mut() {
+                Some(Value::Record(fields)) => fields,
+                _ => return Err("Key is already defined as an array"),
+            },
+            _ => return Err("Key is already defined as a value"),
+        };
+    }
+
+    Ok(table)
+}
+
+/// Turns lists of uniform records into tables, recursively.
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Record(fields) => Value::Record(
+            fields.into_iter().map(|(key, value)| (key, normalize(value))).collect()
+        ),
+        Value::List(items) => list_or_table(items.into_iter().map(normalize).collect()),
+        value => value,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    index: usize,
+}
+
+impl Parser<'_> {
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.index).copied()
+    }
+
+    #[inline]
+    fn rest(&self) -> &str {
+        &self.text[self.index..]
+    }
+
+    /// Skips spaces, tabs and comments, and new lines if `newlines` is true.
+    fn skip_whitespace_and_comments(&mut self, newlines: bool) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t') => self.index += 1,
+                Some(b'\n' | b'\r') if newlines => self.index += 1,
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.index += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), FormatError> {
+        self.skip_whitespace_and_comments(false);
+
+        if self.peek() == Some(byte) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(FormatError::new(self.index, message))
+        }
+    }
+
+    /// Parses a possibly dotted key.
+    fn parse_key(&mut self) -> Result<Vec<String>, FormatError> {
+        let mut path = Vec::new();
+
+        loop {
+            self.skip_whitespace_and_comments(false);
+
+            let start = self.index;
+
+            let key = match self.peek() {
+                Some(b'"') => self.parse_basic_string()?,
+                Some(b'\'') => self.parse_literal_string()?,
+                _ => {
+                    while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-') = self.peek() {
+                        self.index += 1;
+                    }
+
+                    if start == self.index {
+                        return Err(FormatError::new(start, "Expected a key"));
+                    }
+
+                    self.text[start..self.index].to_string()
+                }
+            };
+
+            path.push(key);
+
+            self.skip_whitespace_and_comments(false);
+
+            if self.peek() != Some(b'.') {
+                return Ok(path);
+            }
+
+            self.index += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FormatError> {
+        self.skip_whitespace_and_comments(false);
+
+        let start = self.index;
+
+        match self.peek() {
+            Some(b'"') => Ok(Value::String(self.parse_basic_string()?)),
+            Some(b'\'') => Ok(Value::String(self.parse_literal_string()?)),
+            Some(b'[') => {
+                self.index += 1;
+
+                let mut items = Vec::new();
+
+                loop {
+                    self.skip_whitespace_and_comments(true);
+
+                    if self.peek() == Some(b']') {
+                        self.index += 1;
+                        return Ok(Value::List(items));
+                    }
+
+                    items.push(self.parse_value()?);
+
+                    self.skip_whitespace_and_comments(true);
+
+                    match self.peek() {
+                        Some(b',') => self.index += 1,
+                        Some(b']') => {}
+                        _ => return Err(FormatError::new(self.index, "Expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.index += 1;
+
+                let mut fields = Fields::new();
+
+                self.skip_whitespace_and_comments(false);
+
+                if self.peek() == Some(b'}') {
+                    self.index += 1;
+                    return Ok(Value::Record(fields));
+                }
+
+                loop {
+                    let key_start = self.index;
+                    let path = self.parse_key()?;
+                    self.expect(b'=', "Expected '=' after key")?;
+                    let value = self.parse_value()?;
+
+                    let (last, parents) = path.split_last().unwrap();
+                    let table = table_at(&mut fields, parents)
+                        .map_err(|message| FormatError::new(key_start, message))?;
+
+                    if table.iter().any(|(key, _)| key == last) {
+                        return Err(FormatError::new(key_start, "Key is already defined"));
+                    }
+
+                    table.push((last.clone(), value));
+
+                    self.skip_whitespace_and_comments(false);
+
+                    match self.peek() {
+                        Some(b',') => self.index += 1,
+                        Some(b'}') => {
+                            self.index += 1;
+                            return Ok(Value::Record(fields));
+                        }
+                        _ => return Err(FormatError::new(self.index, "Expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(_) => {
+                while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'+' | b'-' | b'.' | b':') = self.peek() {
+                    self.index += 1;
+                }
+
+                // Allow a space between the date and the time of a datetime.
+                if self.peek() == Some(b' ')
+                    && self.text[start..self.index].len() == 10
+                    && self.text[self.index + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                    self.index += 1;
+
+                    while let Some(b'0'..=b'9' | b'+' | b'-' | b'.' | b':' | b'Z' | b'z') = self.peek() {
+                        self.index += 1;
+                    }
+                }
+
+                parse_scalar(&self.text[start..self.index])
+                    .ok_or(FormatError::new(start, "Expected a value"))
+            }
+            None => Err(FormatError::new(start, "Unexpected end of input")),
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, FormatError> {
+        let start = self.index;
+        let multiline = self.rest().starts_with("\"\"\"");
+
+        self.index += if multiline { 3 } else { 1 };
+
+        if multiline {
+            self.skip_newline();
+        }
+
+        let mut string = String::new();
+
+        loop {
+            let Some(c) = self.rest().chars().next() else {
+                return Err(FormatError::new(start, "Unterminated string"));
+            };
+
+            match c {
+                '"' if !multiline => {
+                    self.index += 1;
+                    return Ok(string);
+                }
+                '"' if self.rest().starts_with("\"\"\"") => {
+                    self.index += 3;
+
+                    // Up to two quotes are allowed right before the closing delimiter.
+                    while self.peek() == Some(b'"') {
+                        string.push('"');
+                        self.index += 1;
+                    }
+
+                    return Ok(string);
+                }
+                '\\' => {
+                    self.index += 1;
+
+                    let escape_start = self.index;
+
+                    match self.peek() {
+                        Some(b'b') => string.push('\u{8}'),
+                        Some(b't') => string.push('\t'),
+                        Some(b'n') => string.push('\n'),
+                        Some(b'f') => string.push('\u{c}'),
+                        Some(b'r') => string.push('\r'),
+                        Some(b'"') => string.push('"'),
+                        Some(b'\\') => string.push('\\'),
+                        Some(b'u' | b'U') => {
+                            let length = if self.peek() == Some(b'u') { 4 } else { 8 };
+
+                            let c = self.text
+                                .get(self.index + 1..self.index + 1 + length)
+                                .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or(FormatError::new(escape_start, "Invalid unicode escape"))?;
+
+                            string.push(c);
+                            self.index += length;
+                        }
+                        Some(b' ' | b'\t' | b'\r' | b'\n') if multiline => {
+                            // A line ending backslash trims all whitespace up to the next text.
+                            while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+                                self.index += 1;
+                            }
+
+                            continue;
+                        }
+                        _ => return Err(FormatError::new(escape_start, "Invalid escape sequence")),
+                    }
+
+                    self.index += 1;
+                }
+                '\n' if !multiline => return Err(FormatError::new(start, "Unterminated string")),
+                c => {
+                    string.push(c);
+                    self.index += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, FormatError> {
+        let start = self.index;
+        let multiline = self.rest().starts_with("'''");
+        let delimiter = if multiline { "'''" } else { "'" };
+
+        self.index += delimiter.len();
+
+        if multiline {
+            self.skip_newline();
+        }
+
+        let Some(length) = self.rest().find(delimiter) else {
+            return Err(FormatError::new(start, "Unterminated string"));
+        };
+
+        let mut string = self.rest()[..length].to_string();
+
+        if !multiline && string.contains('\n') {
+            return Err(FormatError::new(start, "Unterminated string"));
+        }
+
+        self.index += length + delimiter.len();
+
+        // Up to two quotes are allowed right before the closing delimiter.
+        if multiline {
+            while self.peek() == Some(b'\'') {
+                string.push('\'');
+                self.index += 1;
+            }
+        }
+
+        Ok(string)
+    }
+
+    /// Skips a new line directly after the opening delimiter of a multi-line string.
+    fn skip_newline(&mut self) {
+        if self.rest().starts_with("\r\n") {
+            self.index += 2;
+        } else if self.rest().starts_with('\n') {
+            self.index += 1;
+        }
+    }
+}
+
+/// Parses booleans, numbers, dates and times.
+fn parse_scalar(text: &str) -> Option<Value> {
+    match text {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        "inf" | "+inf" => return Some(Value::Number(f64::INFINITY)),
+        "-inf" => return Some(Value::Number(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(Value::Number(f64::NAN)),
+        _ => {}
+    }
+
+    let bytes = text.as_bytes();
+
+    // Dates look like `1979-05-27`, times like `07:32:00`.
+    if (bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-') || (bytes.len() >= 8 && bytes[2] == b':') {
+        return Some(Value::String(text.to_string()));
+    }
+
+    let digits = text.replace('_', "");
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = digits.strip_prefix(prefix) {
+            // `from_str_radix` also accepts a sign, which TOML doesn't allow here.
+            if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+                return None;
+            }
+
+            return i64::from_str_radix(digits, radix).ok().map(Value::Int);
+        }
+    }
+
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+        return None;
+    }
+
+    if digits.contains(['.', 'e', 'E']) {
+        digits.parse().ok().map(Value::Number)
+    } else {
+        digits.parse().ok().map(Value::Int)
+    }
+}
+
+/// Writes a record as a TOML document.
+pub fn write(value: &Value) -> Result<String, RuntimeError> {
+    let Value::Record(fields) = value else {
+        return Err(RuntimeError::TypeError);
+    };
+
+    let mut out = String::new();
+    write_table(fields, &mut Vec::new(), &mut out);
+    Ok(out)
+}
+
+fn write_table(fields: &[(String, Value)], path: &mut Vec<String>, out: &mut String) {
+    let is_table = |value: &Value| matches!(value, Value::Record(_) | Value::Table(_))
+        || matches!(value, Value::List(items) if !items.is_empty() && items.iter().all(|item| matches!(item, Value::Record(_))));
+
+    for (key, value) in fields {
+        if is_table(value) || *value == Value::Nil {
+            continue;
+        }
+
+        write_key(key, out);
+        out.push_str(" = ");
+        write_inline(value, out);
+        out.push('\n');
+    }
+
+    for (key, value) in fields {
+        path.push(key.clone());
+
+        let records = match value {
+            Value::Record(fields) => {
+                out.push_str("\n[");
+                write_path(path, out);
+                out.push_str("]\n");
+                write_table(fields, path, out);
+                Vec::new()
+            }
+            Value::Table(table) => table_to_records(table),
+            Value::List(items) if is_table(value) => items.clone(),
+            _ => Vec::new(),
+        };
+
+        for record in records {
+            if let Value::Record(fields) = record {
+                out.push_str("\n[[");
+                write_path(path, out);
+                out.push_str("]]\n");
+                write_table(&fields, path, out);
+            }
+        }
+
+        path.pop();
+    }
+}
+
+fn write_path(path: &[String], out: &mut String) {
+    for (i, key) in path.iter().enumerate() {
+        if i > 0 {
+            out.push('.');
+        }
+
+        write_key(key, out);
+    }
+}
+
+fn write_key(key: &str, out: &mut String) {
+    if !key.is_empty() && key.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-') {
+        out.push_str(key);
+    } else {
+        write_string(key, out);
+    }
+}
+
+fn write_inline(value: &Value, out: &mut String) {
+    match value {
+        Value::String(s) => write_string(s, out),
+        Value::Number(num) if num.is_nan() => out.push_str("nan"),
+        Value::Number(num) if num.is_infinite() => out.push_str(if *num > 0.0 { "inf" } else { "-inf" }),
+        Value::Number(num) => {
+            let text = num.to_string();
+            out.push_str(&text);
+
+            // Keep whole floats floats when they are read back.
+            if !text.contains(['.', 'e']) {
+                out.push_str(".0");
+            }
+        }
+        Value::Int(int) => {
+            let _ = write!(out, "{}", int);
+        }
+        Value::Quantity(quantity) => write_inline(&Value::Number(quantity.base_amount()), out),
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::List(items) => {
+            out.push('[');
+
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+
+                write_inline(item, out);
+            }
+
+            out.push(']');
+        }
+        Value::Record(fields) => {
+            out.push('{');
+
+            for (i, (key, value)) in fields.iter().enumerate() {
+                out.push_str(if i > 0 { ", " } else { " " });
+                write_key(key, out);
+                out.push_str(" = ");
+                write_inline(value, out);
+            }
+
+            out.push_str(" }");
+        }
+        Value::Table(table) => write_inline(&Value::List(table_to_records(table)), out),
+        Value::Nil | Value::Function(_) => write_string(&value.to_argument(), out),
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_toml() {
+        let value = parse(r#"
+            # A comment
+            title = "a \"b\"" # Trailing comment
+            ports = [ 8000, 8_001,
+                0x1F, ]
+
+            [server.tls]
+            enabled = true
+            inline = { a = 1, b.c = 'd' }
+
+            [[bin]]
+            name = "x"
+        "#);
+
+        assert_eq!(value, Ok(Value::Record(vec![
+            ("title".to_string(), Value::String("a \"b\"".to_string())),
+            ("ports".to_string(), Value::List(vec![
+                Value::Int(8000),
+                Value::Int(8001),
+                Value::Int(31),
+            ])),
+            ("server".to_string(), Value::Record(vec![
+                ("tls".to_string(), Value::Record(vec![
+                    ("enabled".to_string(), Value::Bool(true)),
+                    ("inline".to_string(), Value::Record(vec![
+                        ("a".to_string(), Value::Int(1)),
+                        ("b".to_string(), Value::Record(vec![
+                            ("c".to_string(), Value::String("d".to_string())),
+                        ])),
+                    ])),
+                ])),
+            ])),
+            ("bin".to_string(), Value::Table({
+                let mut table = crate::runtime::Table::new(vec!["name".to_string()]);
+                table.rows.push(vec![Value::String("x".to_string())]);
+                table
+            })),
+        ])));
+    }
+
+    #[test]
+    pub fn test_parse_toml_duplicate_key() {
+        assert_eq!(parse("a = 1\na = 2"), Err(FormatError::new(6, "Key is already defined")));
+        assert_eq!(parse("[a]\nb = 1\n[a]\nc = 2"), Err(FormatError::new(10, "Table is already defined")));
+        assert_eq!(parse("[a.b]\n[a.b]"), Err(FormatError::new(6, "Table is already defined")));
+        assert!(parse("[a.b]\n[a]\n[a.c]").is_ok());
+        assert!(parse("[[a]]\n[a.b]\n[[a]]\n[a.b]").is_ok());
+    }
+
+    #[test]
+    pub fn test_parse_toml_integers() {
+        assert_eq!(parse("a = 0xff\nb = +99\nc = 1_000"), Ok(Value::Record(vec![
+            ("a".to_string(), Value::Int(255)),
+            ("b".to_string(), Value::Int(99)),
+            ("c".to_string(), Value::Int(1000)),
+        ])));
+
+        for text in ["a = 0x+1", "a = 0b-1", "a = 0o", "a = 0b102"] {
+            assert!(parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    pub fn test_write_toml_round_trip() {
+        let text = "a = 1\nb = [\"x\", \"y\"]\n\n[c]\nd = true\n\n[[e]]\nf = 1.5\n";
+        let value = parse(text).unwrap();
+
+        assert_eq!(write(&value).map_err(|_| ()), Ok(text.to_string()));
+    }
+}
\ No newline at end of file
@@ -1,13 +1,34 @@
 mod scope;
 mod eval;
 mod resolve;
+mod table;
+mod builtins;
+mod pipeline;
+pub mod formats;
 
-use crate::runtime::scope::Scope;
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::diagnostics::Diagnostic;
+use crate::read::bytes::Index;
+use crate::units::{Dimension, Quantity};
 use crossterm::style::{Color, SetForegroundColor};
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::ops::Range;
 
 pub use scope::*;
 pub use eval::*;
+pub use table::*;
+pub use pipeline::*;
+pub use builtins::change_setting;
+
+/// The signature of builtin functions. Arguments are passed unevaluated, the input is
+/// the value of the previous pipeline stage or [Value::Nil].
+pub type Builtin = fn(
+    scope: &mut Scope,
+    arguments: &[Span<Expression>],
+    input: Value
+) -> Result<Value, RuntimeError>;
 
 #[derive(Debug)]
 pub struct Variable {
@@ -15,12 +36,21 @@ pub struct Variable {
     pub value: Value,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Value {
     Number(f64),
+    /// An integer. Arithmetic on two integers is checked and fails on overflow.
+    Int(i64),
+    /// An amount with a unit, like `10MB` or `250ms`.
+    Quantity(Quantity),
+    Bool(bool),
     Nil,
-    Function(fn(scope: &mut Scope) -> Result<Value, RuntimeError>),
+    Function(Builtin),
     String(String),
+    List(Vec<Value>),
+    /// Named fields in insertion order.
+    Record(Vec<(String, Value)>),
+    Table(Table),
 }
 
 impl Value {
@@ -30,6 +60,74 @@ impl Value {
             _ => None
         }
     }
+
+    /// Describes the kind of the value for error messages, like `an integer`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "a number",
+            Value::Int(_) => "an integer",
+            Value::Quantity(quantity) => match quantity.unit.dimension {
+                Dimension::Size => "a size",
+                Dimension::Duration => "a duration",
+                Dimension::Length => "a length",
+                Dimension::Mass => "a mass",
+            },
+            Value::Bool(_) => "a bool",
+            Value::Nil => "nil",
+            Value::Function(_) => "a function",
+            Value::String(_) => "a string",
+            Value::List(_) => "a list",
+            Value::Record(_) => "a record",
+            Value::Table(_) => "a table",
+        }
+    }
+
+    /// Returns the value of a number or integer as a float.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Number(num) => Some(*num),
+            Value::Int(int) => Some(*int as f64),
+            _ => None,
+        }
+    }
+
+    /// Converts the value into a string that can be passed to an external program.
+    pub fn to_argument(&self) -> String {
+        match self {
+            Value::String(s) => s.clone(),
+            Value::Number(num) => num.to_string(),
+            Value::Int(int) => int.to_string(),
+            // Unlike the display, which is rounded.
+            Value::Quantity(quantity) => format!("{}{}", quantity.amount, quantity.unit.name),
+            Value::Bool(b) => b.to_string(),
+            Value::Nil => String::new(),
+            Value::Function(_) => "[function]".to_string(),
+            Value::List(list) => format!("[list {} items]", list.len()),
+            Value::Record(fields) => format!("[record {} fields]", fields.len()),
+            Value::Table(table) => format!("[table {} rows]", table.rows.len()),
+        }
+    }
+
+    /// Compares two values of the same kind, integers and floats compare by
+    /// their numeric value and quantities if they have the same dimension.
+    /// Nil is ordered before everything else.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left.partial_cmp(right),
+            (Value::Int(left), Value::Int(right)) => Some(left.cmp(right)),
+            (Value::Int(left), Value::Number(right)) => (*left as f64).partial_cmp(right),
+            (Value::Number(left), Value::Int(right)) => left.partial_cmp(&(*right as f64)),
+            (Value::Quantity(left), Value::Quantity(right)) => (left.unit.dimension == right.unit.dimension)
+                .then(|| left.base_amount().partial_cmp(&right.base_amount()))
+                .flatten(),
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+            (Value::Nil, Value::Nil) => Some(Ordering::Equal),
+            (Value::Nil, _) => Some(Ordering::Less),
+            (_, Value::Nil) => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Value {
@@ -37,9 +135,32 @@ impl Display for Value {
         match self {
             Self::Number(num) => write!(
                 f,
-                "{}{}{}",
+                "{}{}{}{}",
                 SetForegroundColor(Color::Blue),
                 num,
+                // Whole floats keep a fraction to tell them apart from integers.
+                if num.is_finite() && num.fract() == 0.0 { ".0" } else { "" },
+                SetForegroundColor(Color::Reset)
+            ),
+            Self::Int(int) => write!(
+                f,
+                "{}{}{}",
+                SetForegroundColor(Color::Blue),
+                int,
+                SetForegroundColor(Color::Reset)
+            ),
+            Self::Quantity(quantity) => write!(
+                f,
+                "{}{}{}",
+                SetForegroundColor(Color::Blue),
+                quantity,
+                SetForegroundColor(Color::Reset)
+            ),
+            Self::Bool(b) => write!(
+                f,
+                "{}{}{}",
+                SetForegroundColor(Color::Magenta),
+                b,
                 SetForegroundColor(Color::Reset)
             ),
             Self::Nil => write!(
@@ -61,6 +182,64 @@ impl Display for Value {
                 s,
                 SetForegroundColor(Color::Reset)
             ),
+            Self::List(list) => {
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+
+                    write!(
+                        f,
+                        "{}{:>3}{} {}",
+                        SetForegroundColor(Color::Grey),
+                        i,
+                        SetForegroundColor(Color::Reset),
+                        item
+                    )?;
+                }
+
+                Ok(())
+            }
+            Self::Record(fields) => {
+                let width = fields
+                    .iter()
+                    .map(|(name, _)| name.chars().count())
+                    .max()
+                    .unwrap_or(0);
+
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+
+                    write!(
+                        f,
+                        "{}{}{}",
+                        SetForegroundColor(Color::Green),
+                        name,
+                        SetForegroundColor(Color::Reset)
+                    )?;
+
+                    match value {
+                        // Nested structures are written indented below their name.
+                        Value::List(_) | Value::Record(_) | Value::Table(_) => {
+                            for line in value.to_string().trim_end().lines() {
+                                write!(f, "\n  {}", line)?;
+                            }
+                        }
+                        value => write!(
+                            f,
+                            "{:padding$} {}",
+                            "",
+                            value,
+                            padding = width - name.chars().count()
+                        )?,
+                    }
+                }
+
+                Ok(())
+            }
+            Self::Table(table) => write!(f, "{}", table),
         }
     }
 }
@@ -68,7 +247,92 @@ impl Display for Value {
 #[derive(Debug)]
 #[repr(u8)]
 pub enum RuntimeError {
-    UndefinedVariable,
+    /// An external program that isn't in the PATH.
+    CommandNotFound,
     UnimplementedFeature,
-    UnimplementedError
+    InvalidArgument,
+    TypeError,
+    IntegerOverflow,
+    DivisionByZero,
+    /// Quantities with units of different dimensions, like `1MB + 1s`.
+    IncompatibleUnits,
+    IoError,
+    /// A part of the input that couldn't be parsed, see [Expression::Error].
+    ///
+    /// [Expression::Error]: crate::read::ast::Expression::Error
+    InvalidSyntax,
+    /// Malformed input to a format parser, like JSON. Line and column start at one.
+    ParseError {
+        line: usize,
+        column: usize,
+        message: &'static str,
+    },
+    /// An error caused by the expression at `range` of the input. The labels point
+    /// at related parts of the input.
+    Located {
+        error: Box<RuntimeError>,
+        range: Range<Index>,
+        labels: Vec<(Range<Index>, String)>,
+    },
+}
+
+impl RuntimeError {
+    /// Locates the error at the given range of the input, unless it is located already.
+    pub fn at(self, range: Range<Index>) -> Self {
+        match self {
+            Self::Located { .. } => self,
+            error => Self::Located {
+                error: Box::new(error),
+                range,
+                labels: Vec::new(),
+            },
+        }
+    }
+
+    /// Returns a diagnostic for the error, with labels if the error is located.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::new(self.to_string());
+
+        match self {
+            Self::CommandNotFound => diagnostic
+                .with_hint("check the spelling, or whether the program is installed and in your PATH"),
+            Self::IntegerOverflow => diagnostic
+                .with_hint("calculate with floats for larger numbers, like `2.0 ** 64`"),
+            Self::IncompatibleUnits => diagnostic
+                .with_note("only quantities of the same dimension can be added, compared or converted"),
+            Self::ParseError { line, column, .. } => diagnostic
+                .with_note(format!("at line {}, column {} of the parsed text", line, column)),
+            Self::Located { error, range, labels } => {
+                let mut diagnostic = error.diagnostic().with_primary(range.clone(), match **error {
+                    Self::CommandNotFound => "not found",
+                    _ => "",
+                });
+
+                for (range, message) in labels {
+                    diagnostic = diagnostic.with_secondary(range.clone(), message.clone());
+                }
+
+                diagnostic
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CommandNotFound => f.write_str("Command not found"),
+            Self::UnimplementedFeature => f.write_str("Not implemented yet"),
+            Self::InvalidArgument => f.write_str("Invalid argument"),
+            Self::TypeError => f.write_str("Mismatched types"),
+            Self::IntegerOverflow => f.write_str("Integer overflow"),
+            Self::DivisionByZero => f.write_str("Division by zero"),
+            Self::IncompatibleUnits => f.write_str("Incompatible units"),
+            Self::IoError => f.write_str("Failed to read or write"),
+            Self::InvalidSyntax => f.write_str("Invalid syntax"),
+            Self::ParseError { message, .. } => f.write_str(message),
+            Self::Located { error, .. } => write!(f, "{}", error),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,361 @@
+use crate::read::ast::Expression;
+use crate::read::bytes::Span;
+use crate::runtime::formats::json;
+use crate::read::bytes::Index;
+use crate::runtime::{eval, eval_with_input, lookup, set_variable, RuntimeError, Scope, Value};
+use std::io::ErrorKind;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+/// How many bytes of the end of the stderr of a pipeline are kept, for `explain`.
+const STDERR_TAIL: usize = 4096;
+
+/// The output of a pipeline stage.
+enum Output {
+    /// A structured value, produced by a builtin or an expression.
+    Value(Value),
+    /// A running external program whose stdout is piped.
+    Process(Child),
+}
+
+/// Runs the stages of a pipeline, connecting them according to their kind:
+///
+/// * Values are passed to builtins as their input.
+/// * The stdout of an external program is connected to the stdin of the next program.
+/// * Values piped into an external program are serialized to its stdin, see [serialize].
+/// * The stdout of an external program piped into a builtin is captured as a [Value::String].
+///
+/// If the last stage is an external program, its stdout is inherited and the result is
+/// [Value::Nil] on success or the exit code otherwise. The exit code of a pipeline with
+/// programs is also stored in the `status` variable. It is the code of the rightmost
+/// program that failed, also if its stdout was captured, like `pipefail` in other shells.
+/// Programs before the last one that are killed by a signal don't count, as that's usually
+/// SIGPIPE from a later program that stopped reading, like in `yes | head`.
+///
+/// The stderr of programs is inherited. With the `capture-stderr` setting on, it is piped
+/// through the shell to the terminal, and its tail is also kept in the `stderr-tail`
+/// variable. Programs then don't see a terminal on stderr, so they may print it differently.
+pub fn run_pipeline(
+    scope: &mut Scope,
+    stages: &[Span<Expression>],
+    input: Value
+) -> Result<Value, RuntimeError> {
+    let mut output = Output::Value(input);
+
+    // Programs before the last stage, in the order of their stages.
+    let mut upstream = Vec::new();
+    let mut stderr_tails = Vec::new();
+
+    let result = run_stages(scope, stages, &mut output, &mut upstream, &mut stderr_tails);
+
+    let mut status = None;
+
+    for mut child in upstream {
+        status = match child.wait().map(|status| status.code()) {
+            Ok(Some(code)) if code != 0 => Some(code as i64),
+            _ => status.or(Some(0)),
+        };
+    }
+
+    let output = match (result, output) {
+        (Ok(()), output) => output,
+        (Err(error), Output::Process(mut child)) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(error);
+        }
+        (Err(error), _) => return Err(error),
+    };
+
+    let result = match output {
+        Output::Value(value) => {
+            if let Some(status) = status {
+                set_variable(scope, "status", Value::Int(status));
+            }
+
+            Ok(value)
+        }
+        Output::Process(mut child) => child.wait().map_err(|_| RuntimeError::IoError).map(|exit| {
+            // Programs that were killed by a signal have no exit code.
+            let code = match exit.code().unwrap_or(1) as i64 {
+                0 => status.unwrap_or(0),
+                code => code,
+            };
+
+            set_variable(scope, "status", Value::Int(code));
+
+            if code == 0 {
+                Value::Nil
+            } else {
+                Value::Int(code)
+            }
+        }),
+    };
+
+    // The programs have exited, so their stderr is closed.
+    if !stderr_tails.is_empty() {
+        let mut tail = stderr_tails
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        tail.drain(..tail.len().saturating_sub(STDERR_TAIL));
+        set_variable(scope, "stderr-tail", Value::String(String::from_utf8_lossy(&tail).into_owned()));
+    }
+
+    result
+}
+
+fn run_stages(
+    scope: &mut Scope,
+    stages: &[Span<Expression>],
+    output: &mut Output,
+    upstream: &mut Vec<Child>,
+    stderr_tails: &mut Vec<JoinHandle<Vec<u8>>>
+) -> Result<(), RuntimeError> {
+    let capture_stderr = lookup(scope, "capture-stderr").and_then(Value::get_string) == Some("on");
+
+    for (i, stage) in stages.iter().enumerate() {
+        *output = match external_command(scope, stage) {
+            Some((name, arguments)) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| match argument.value {
+                        Expression::Numeral { text, .. } => Ok(text.to_string()),
+                        _ => eval(scope, argument).map(|value| value.to_argument()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut command = Command::new(name);
+                command.args(arguments);
+
+                let mut stdin_bytes = None;
+
+                match std::mem::replace(output, Output::Value(Value::Nil)) {
+                    Output::Value(Value::Nil) => {}
+                    Output::Value(value) => {
+                        command.stdin(Stdio::piped());
+                        stdin_bytes = Some(serialize(&value));
+                    }
+                    Output::Process(mut child) => {
+                        command.stdin(child.stdout.take().map_or_else(Stdio::null, Stdio::from));
+                        upstream.push(child);
+                    }
+                }
+
+                if i + 1 < stages.len() {
+                    command.stdout(Stdio::piped());
+                }
+
+                if capture_stderr {
+                    command.stderr(Stdio::piped());
+                }
+
+                let mut child = command.spawn().map_err(|error| {
+                    let error = match error.kind() {
+                        ErrorKind::NotFound => RuntimeError::CommandNotFound,
+                        _ => RuntimeError::IoError,
+                    };
+
+                    error.at(stage.range.start..stage.range.start + name.len() as Index)
+                })?;
+
+                if let Some(stderr) = child.stderr.take() {
+                    stderr_tails.push(pass_stderr(stderr));
+                }
+
+                if let (Some(bytes), Some(mut stdin)) = (stdin_bytes, child.stdin.take()) {
+                    // Written on another thread, so a program that doesn't read
+                    // its whole input can't block the pipeline.
+                    thread::spawn(move || {
+                        let _ = stdin.write_all(&bytes);
+                    });
+                }
+
+                Output::Process(child)
+            }
+            None => {
+                let input = match std::mem::replace(output, Output::Value(Value::Nil)) {
+                    Output::Value(value) => value,
+                    Output::Process(mut child) => {
+                        let captured = capture(&mut child);
+                        upstream.push(child);
+                        captured?
+                    }
+                };
+
+                Output::Value(eval_with_input(scope, stage, input)?)
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Returns the name and arguments if the stage invokes an external program.
+fn external_command<'a, 'b>(
+    scope: &Scope,
+    stage: &'a Span<Expression<'b>>
+) -> Option<(&'b str, &'a [Span<Expression<'b>>])> {
+    match &stage.value {
+        Expression::CommandInvocation { name, arguments } => match lookup(scope, name) {
+            None => Some((name, arguments)),
+            Some(Value::Function(_)) => None,
+            Some(_) if arguments.is_empty() => None,
+            Some(_) => Some((name, arguments)),
+        },
+        _ => None,
+    }
+}
+
+/// Writes the stderr of a program to the stderr of the shell as it arrives, and returns
+/// its end once it is closed.
+fn pass_stderr(mut stderr: ChildStderr) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut tail = Vec::new();
+        let mut buffer = [0; 4096];
+        let mut shell_stderr = std::io::stderr();
+
+        while let Ok(length @ 1..) = stderr.read(&mut buffer) {
+            let _ = shell_stderr.write_all(&buffer[..length]);
+            let _ = shell_stderr.flush();
+
+            tail.extend_from_slice(&buffer[..length]);
+            tail.drain(..tail.len().saturating_sub(STDERR_TAIL));
+        }
+
+        tail
+    })
+}
+
+/// Waits for the program to exit and returns its stdout as a [Value::String]. Its exit
+/// status is kept by the child, for the `status` variable.
+fn capture(child: &mut Child) -> Result<Value, RuntimeError> {
+    let mut bytes = Vec::new();
+
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_end(&mut bytes).map_err(|_| RuntimeError::IoError)?;
+    }
+
+    child.wait().map_err(|_| RuntimeError::IoError)?;
+
+    Ok(Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Serializes a value to be written to the stdin of an external program.
+///
+/// Strings are written as they are, lists as one line per item and tables
+/// as one JSON object per row. Everything else is written as a single line.
+pub fn serialize(value: &Value) -> Vec<u8> {
+    let mut text = String::new();
+
+    match value {
+        Value::String(s) => text.push_str(s),
+        Value::List(list) => {
+            for item in list {
+                match item {
+                    Value::String(s) => text.push_str(s),
+                    item => json::write(item, &mut text),
+                }
+
+                text.push('\n');
+            }
+        }
+        Value::Table(table) => {
+            for row in &table.rows {
+                json::write_row(&table.columns, row, &mut text);
+                text.push('\n');
+            }
+        }
+        value => {
+            text.push_str(&value.to_argument());
+            text.push('\n');
+        }
+    }
+
+    text.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::buffered::Buffered;
+    use crate::read::bytes::Cursor;
+    use crate::read::lex::Lexer;
+    use crate::read::parse::parse;
+    use crate::runtime::{new_root_scope, run_input, Table};
+
+    fn run(scope: &mut Scope, input: &str) -> Result<Value, RuntimeError> {
+        run_input(scope, input, Value::Nil)
+    }
+
+    fn lines(lines: &[&str]) -> Value {
+        Value::List(lines.iter().map(|line| Value::String(line.to_string())).collect())
+    }
+
+    // The pipelines run Unix programs.
+    #[cfg(unix)]
+    #[test]
+    pub fn test_run_pipeline() {
+        let mut scope = new_root_scope();
+
+        assert_eq!(run(&mut scope, "printf 'a\\nb\\n' | lines").ok(), Some(lines(&["a", "b"])));
+        assert_eq!(run(&mut scope, "'abc' | tr a-z A-Z | lines").ok(), Some(lines(&["ABC"])));
+        assert_eq!(run(&mut scope, "printf 'b\\na\\n' | sort | lines | length").ok(), Some(Value::Int(2)));
+        assert_eq!(run(&mut scope, "echo 01 1.50 0x10 | lines").ok(), Some(lines(&["01 1.50 0x10"])));
+
+        assert_eq!(run(&mut scope, "true").ok(), Some(Value::Nil));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(0)));
+        assert_eq!(run(&mut scope, "sh -c 'exit 3'").ok(), Some(Value::Int(3)));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(3)));
+
+        // Programs killed by a signal fail with the same code as the status.
+        assert_eq!(run(&mut scope, "sh -c 'kill -9 $$'").ok(), Some(Value::Int(1)));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(1)));
+
+        // The rightmost program that fails sets the status, also if its stdout is captured.
+        assert_eq!(run(&mut scope, "sh -c 'exit 2' | lines").ok(), Some(lines(&[])));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(2)));
+        assert_eq!(run(&mut scope, "sh -c 'exit 4' | cat").ok(), Some(Value::Int(4)));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(4)));
+        assert_eq!(run(&mut scope, "sh -c 'exit 4' | sh -c 'exit 5' | lines").ok(), Some(lines(&[])));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(5)));
+        assert_eq!(run(&mut scope, "yes | head -c 0").ok(), Some(Value::Nil));
+        assert_eq!(scope.get("status").map(|variable| &variable.value), Some(&Value::Int(0)));
+
+        // Stderr is only captured when the setting is on. The programs print nothing, so
+        // that the output of the tests stays clean.
+        let stderr_tail = |scope: &Scope| scope.get("stderr-tail").map(|variable| variable.value.clone());
+        run(&mut scope, "true").unwrap();
+        assert_eq!(stderr_tail(&scope), None);
+
+        run(&mut scope, "set capture-stderr on").unwrap();
+        run(&mut scope, "true | lines").unwrap();
+        assert_eq!(stderr_tail(&scope), Some(Value::String(String::new())));
+    }
+
+    #[test]
+    pub fn test_command_not_found() {
+        let expression = parse(&mut Buffered::new(Lexer::new(Cursor::new("shesh-missing-program --version | lines")))).unwrap();
+
+        assert!(matches!(
+            eval(&mut new_root_scope(), &expression),
+            Err(RuntimeError::Located { error, range, .. }) if matches!(*error, RuntimeError::CommandNotFound) && range == (0..21)
+        ));
+    }
+
+    #[test]
+    pub fn test_serialize() {
+        let text = |value| String::from_utf8(serialize(&value)).unwrap();
+
+        assert_eq!(text(Value::String("a\nb".to_string())), "a\nb");
+        assert_eq!(text(lines(&["a", "b"])), "a\nb\n");
+        assert_eq!(text(Value::List(vec![Value::Int(1), Value::Bool(true)])), "1\ntrue\n");
+        assert_eq!(text(Value::Int(42)), "42\n");
+
+        let mut table = Table::new(vec!["name".to_string(), "size".to_string()]);
+        table.rows.push(vec![Value::String("a".to_string()), Value::Int(1)]);
+        assert_eq!(text(Value::Table(table)), "{\"name\":\"a\",\"size\":1}\n");
+    }
+}
\ No newline at end of file
@@ -1,10 +1,9 @@
-use crate::runtime::{Value, Variable};
+use crate::runtime::{builtins, Value, Variable};
 use crossterm::cursor::MoveTo;
-use crossterm::{execute, queue};
+use crossterm::execute;
 use crossterm::terminal::{Clear, ClearType};
 use std::collections::HashMap;
-use std::io::{stdout, Write};
-use crossterm::style::Print;
+use std::io::stdout;
 
 pub type Scope = HashMap<String, Variable>;
 
@@ -20,7 +19,7 @@ pub fn new_root_scope() -> Scope {
 
     scope.insert("clear".to_string(), Variable {
         mutable: false,
-        value: Value::Function(|_| {
+        value: Value::Function(|_, _, _| {
             execute!(stdout(), Clear(ClearType::All), Clear(ClearType::Purge), MoveTo(0, 0))
                 .unwrap();
 
@@ -30,14 +29,14 @@ pub fn new_root_scope() -> Scope {
 
     scope.insert("exit".to_string(), Variable {
         mutable: false,
-        value: Value::Function(|_| {
+        value: Value::Function(|_, _, _| {
             std::process::exit(0)
         })
     });
 
     scope.insert("pwd".to_string(), Variable {
         mutable: false,
-        value: Value::Function(|_| {
+        value: Value::Function(|_, _, _| {
             if let Ok(cwd) = std::env::current_dir() {
                 println!("{}", cwd.display());
             } else {
@@ -50,33 +49,19 @@ pub fn new_root_scope() -> Scope {
 
     scope.insert("debug_print_scope".to_string(), Variable {
         mutable: false,
-        value: Value::Function(|scope| {
+        value: Value::Function(|scope, _, _| {
             println!("{:#?}", scope);
             Ok(Value::Nil)
         })
     });
 
-    scope.insert("ls".to_string(), Variable {
-        mutable: false,
-        value: Value::Function(|_| {
-            let mut stdout = stdout();
-
-            if let Ok(entries) = std::fs::read_dir(".") {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        queue!(stdout, Print(entry.file_name().to_string_lossy()), Print("\n"))
-                            .unwrap();
-                    }
-                }
-
-                stdout.flush().unwrap();
-            } else {
-                eprintln!("Failed to read directory");
-            }
-
-            Ok(Value::Nil)
-        })
-    });
+    builtins::register(&mut scope);
 
     scope
+}
+
+/// Sets a variable that the shell updates itself, like `status`. It can't be changed
+/// with `set`.
+pub fn set_variable(scope: &mut Scope, name: &str, value: Value) {
+    scope.insert(name.to_string(), Variable { mutable: false, value });
 }
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::runtime::Value;
+use crossterm::style::{Color, SetForegroundColor};
+use crossterm::terminal::size;
+use std::fmt::{Display, Formatter};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A table of values with named columns. Every row has exactly one value per column.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    #[inline]
+    pub fn new(columns: Vec<String>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Returns the index of the column with the given name.
+    #[inline]
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    /// Renders the table with aligned columns. If the table is wider than `max_width`,
+    /// the widest columns are shrunk and their cells truncated.
+    pub fn render(&self, f: &mut Formatter<'_>, max_width: usize) -> std::fmt::Result {
+        /// The minimum width a column is shrunk to.
+        const MIN_COLUMN_WIDTH: usize = 4;
+
+        let cells = self.rows
+            .iter()
+            .map(|row| row.iter().map(cell_text).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut widths = self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| cells
+                .iter()
+                .map(|row| row[i].width())
+                .fold(column.width(), usize::max))
+            .collect::<Vec<_>>();
+
+        // Every column is padded with a space on both sides.
+        let mut total_width = widths.iter().map(|width| width + 2).sum::<usize>();
+
+        while total_width > max_width {
+            let Some(widest) = widths.iter_mut().max() else {
+                break;
+            };
+
+            if *widest <= MIN_COLUMN_WIDTH {
+                break;
+            }
+
+            *widest -= 1;
+            total_width -= 1;
+        }
+
+        write!(f, "{}", SetForegroundColor(Color::Green))?;
+
+        for (column, width) in self.columns.iter().zip(&widths) {
+            write!(f, " {} ", pad(&truncate(column, *width), *width, false))?;
+        }
+
+        writeln!(f, "{}", SetForegroundColor(Color::Reset))?;
+
+        for (row, texts) in self.rows.iter().zip(&cells) {
+            for ((value, text), width) in row.iter().zip(texts).zip(&widths) {
+                let is_numeric = matches!(value, Value::Number(_) | Value::Int(_) | Value::Quantity(_));
+                let text = pad(&truncate(text, *width), *width, is_numeric);
+
+                write!(f, " {}{}{} ", SetForegroundColor(cell_color(value)), text, SetForegroundColor(Color::Reset))?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the text of a value as it appears in a table cell, without any styling.
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Nil => String::new(),
+        Value::Quantity(quantity) => quantity.to_string(),
+        value => value.to_argument(),
+    }
+}
+
+fn cell_color(value: &Value) -> Color {
+    match value {
+        Value::Number(_) | Value::Int(_) | Value::Quantity(_) => Color::Blue,
+        Value::Bool(_) => Color::Magenta,
+        Value::Function(_) => Color::Yellow,
+        _ => Color::Reset,
+    }
+}
+
+/// Shortens the text to at most `width` columns on the terminal, marking the cut with an
+/// ellipsis. Graphemes are kept whole, so a wide one that doesn't fit is left out.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+
+    for grapheme in text.graphemes(true) {
+        truncated_width += grapheme.width();
+
+        if truncated_width >= width {
+            break;
+        }
+
+        truncated.push_str(grapheme);
+    }
+
+    truncated.push('…');
+    truncated
+}
+
+/// Pads the text with spaces to `width` columns on the terminal, on the left if it's
+/// aligned to the right. The formatting width of [std::fmt] counts chars instead.
+fn pad(text: &str, width: usize, align_right: bool) -> String {
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+
+    if align_right {
+        padding + text
+    } else {
+        text.to_string() + &padding
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let max_width = size().map_or(usize::MAX, |(columns, _)| columns as usize);
+        self.render(f, max_width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders the table at the given width, without colors.
+    fn render(table: &Table, max_width: usize) -> String {
+        struct Rendered<'a>(&'a Table, usize);
+
+        impl Display for Rendered<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                self.0.render(f, self.1)
+            }
+        }
+
+        let text = Rendered(table, max_width).to_string();
+        let mut plain = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                plain.push(c);
+            }
+        }
+
+        plain
+    }
+
+    #[test]
+    pub fn test_render() {
+        let mut table = Table::new(vec!["name".to_string(), "size".to_string(), "note".to_string()]);
+
+        table.rows.push(vec![Value::String("a.txt".to_string()), Value::Int(12), Value::Nil]);
+        table.rows.push(vec![Value::String("b".to_string()), Value::Int(3), Value::String("read me".to_string())]);
+
+        assert_eq!(
+            render(&table, 80),
+            " name   size  note    \n a.txt    12          \n b         3  read me \n"
+        );
+
+        assert_eq!(
+            render(&table, 16),
+            " name  size  note \n a.t…    12       \n b        3  rea… \n"
+        );
+
+        // Wide chars take two columns, and aren't cut in half.
+        let mut table = Table::new(vec!["name".to_string(), "size".to_string()]);
+
+        table.rows.push(vec![Value::String("日本語.txt".to_string()), Value::Int(1)]);
+        table.rows.push(vec![Value::String("a".to_string()), Value::Int(22)]);
+
+        assert_eq!(render(&table, 80), " name        size \n 日本語.txt     1 \n a             22 \n");
+        assert_eq!(render(&table, 12), " name  size \n 日…      1 \n a       22 \n");
+    }
+}
\ No newline at end of file
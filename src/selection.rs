@@ -0,0 +1,98 @@
+//! # Text selected with Shift and the arrow keys.
+
+use crate::text_box::TextBox;
+use std::ops::Range;
+
+/// The selection is between the anchor and the cursor.
+pub struct Selection {
+    anchor: Option<usize>,
+}
+
+impl Selection {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { anchor: None }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.anchor = None;
+    }
+
+    /// Starts a selection at the cursor, unless one is active. Call this before moving
+    /// the cursor to extend the selection.
+    #[inline]
+    pub fn extend(&mut self, text_box: &TextBox) {
+        self.anchor.get_or_insert(text_box.chars_left_from_cursor());
+    }
+
+    /// Returns the selected chars, or `None` if nothing is selected.
+    pub fn range(&self, text_box: &TextBox) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let cursor = text_box.chars_left_from_cursor();
+
+        Some(anchor.min(cursor)..anchor.max(cursor)).filter(|range| !range.is_empty())
+    }
+
+    /// Returns the selected bytes of the input, to highlight them.
+    pub fn bytes(&self, text_box: &TextBox) -> Option<Range<usize>> {
+        let range = self.range(text_box)?;
+        let (left, right) = text_box.parts();
+
+        let byte = |index| {
+            left.char_indices()
+                .chain(right.char_indices().map(|(byte, c)| (left.len() + byte, c)))
+                .nth(index)
+                .map_or(left.len() + right.len(), |(byte, _)| byte)
+        };
+
+        Some(byte(range.start)..byte(range.end))
+    }
+
+    /// Returns the selected text, and removes it from the input if `cut` is set.
+    pub fn take(&mut self, text_box: &mut TextBox, cut: bool) -> Option<String> {
+        let range = self.range(text_box)?;
+        self.anchor = None;
+
+        if cut {
+            text_box.move_cursor_to(range.end);
+            Some(text_box.remove_n_chars_left(range.len()))
+        } else {
+            let (left, right) = text_box.parts();
+            Some(left.chars().chain(right.chars()).skip(range.start).take(range.len()).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_selection() {
+        let mut text_box = TextBox::new();
+        text_box.insert_str("ls ∂src");
+
+        let mut selection = Selection::new();
+        assert_eq!(selection.range(&text_box), None);
+
+        selection.extend(&text_box);
+        text_box.move_cursor_left();
+        text_box.move_cursor_left();
+        text_box.move_cursor_left();
+        selection.extend(&text_box);
+        text_box.move_cursor_left();
+
+        assert_eq!(selection.range(&text_box), Some(3..7));
+        assert_eq!(selection.bytes(&text_box), Some(3..9));
+        assert_eq!(selection.take(&mut text_box, false), Some("∂src".to_string()));
+        assert_eq!(text_box.parts(), ("ls ", "∂src"));
+
+        selection.extend(&text_box);
+        text_box.move_cursor_word_left();
+
+        assert_eq!(selection.take(&mut text_box, true), Some("ls ".to_string()));
+        assert_eq!(text_box.parts(), ("", "∂src"));
+        assert_eq!(selection.range(&text_box), None);
+    }
+}
\ No newline at end of file
@@ -1,5 +1,7 @@
 use std::fmt::{Debug, Formatter};
 use std::mem::{transmute, MaybeUninit};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Clone)]
 pub struct TextBox {
@@ -60,9 +62,11 @@ impl TextBox {
             self.chars_right_from_cursor += n;
 
             let mut chars_to_pass = n;
-            let mut bytes_to_move = 1;
+            let mut bytes_to_move = 0;
 
             while chars_to_pass > 0 {
+                bytes_to_move += 1;
+
                 while unsafe { self.buffer[self.gap_start - bytes_to_move].assume_init() } & 0b1100_0000 == 0b1000_0000 {
                     bytes_to_move += 1;
                 }
@@ -115,13 +119,110 @@ impl TextBox {
         self.gap_end = new_gap_end;
     }
 
+    /// Moves the cursor one grapheme cluster to the left, so that a character is never
+    /// split from its combining marks.
+    pub fn move_cursor_left(&mut self) {
+        let (left, _) = self.parts();
+
+        if let Some(grapheme) = left.graphemes(true).next_back() {
+            self.move_cursor_n_chars_left(grapheme.chars().count());
+        }
+    }
+
+    /// Moves the cursor one grapheme cluster to the right.
+    pub fn move_cursor_right(&mut self) {
+        let (_, right) = self.parts();
+
+        if let Some(grapheme) = right.graphemes(true).next() {
+            self.move_cursor_n_chars_right(grapheme.chars().count());
+        }
+    }
+
+    /// Moves the cursor in front of the char at `index`.
+    pub fn move_cursor_to(&mut self, index: usize) {
+        if index < self.chars_left_from_cursor {
+            self.move_cursor_n_chars_left(self.chars_left_from_cursor - index);
+        } else {
+            self.move_cursor_n_chars_right(index - self.chars_left_from_cursor);
+        }
+    }
+
+    /// Moves the cursor to the start of the current line.
+    pub fn move_cursor_to_line_start(&mut self) {
+        let (left, _) = self.parts();
+        let line = &left[left.rfind('\n').map_or(0, |index| index + 1)..];
+
+        self.move_cursor_n_chars_left(line.chars().count());
+    }
+
+    /// Moves the cursor to the end of the current line.
+    pub fn move_cursor_to_line_end(&mut self) {
+        let (_, right) = self.parts();
+        let line = &right[..right.find('\n').unwrap_or(right.len())];
+
+        self.move_cursor_n_chars_right(line.chars().count());
+    }
+
+    /// Moves the cursor to the start of the word left from it, or of the previous word
+    /// if it is at the start of a word. Words consist of letters, digits and underscores.
+    pub fn move_cursor_word_left(&mut self) {
+        let (left, _) = self.parts();
+        let n = chars_of_word(left.graphemes(true).rev(), is_word);
+
+        self.move_cursor_n_chars_left(n);
+    }
+
+    /// Moves the cursor to the end of the word right from it, or of the next word.
+    pub fn move_cursor_word_right(&mut self) {
+        let (_, right) = self.parts();
+        let n = chars_of_word(right.graphemes(true), is_word);
+
+        self.move_cursor_n_chars_right(n);
+    }
+
+    /// Moves the cursor to the same display column in the previous line, or to the end of
+    /// the previous line if it is shorter. Returns `false` if the cursor is in the first line.
+    pub fn move_cursor_up(&mut self) -> bool {
+        let (left, _) = self.parts();
+
+        let Some(line_start) = left.rfind('\n') else {
+            return false;
+        };
+
+        let column = left[line_start + 1..].width();
+        let previous_line_start = left[..line_start].rfind('\n').map_or(0, |index| index + 1);
+        let previous_line = &left[previous_line_start..line_start];
+
+        let chars_after_column = previous_line[prefix_with_width(previous_line, column).len()..].chars().count();
+
+        self.move_cursor_n_chars_left(left[line_start..].chars().count() + chars_after_column);
+        true
+    }
+
+    /// Moves the cursor to the same display column in the next line, or to the end of the
+    /// next line if it is shorter. Returns `false` if the cursor is in the last line.
+    pub fn move_cursor_down(&mut self) -> bool {
+        let (left, right) = self.parts();
+
+        let Some(line_end) = right.find('\n') else {
+            return false;
+        };
+
+        let column = left[left.rfind('\n').map_or(0, |index| index + 1)..].width();
+
+        let next_line = &right[line_end + 1..];
+        let next_line = &next_line[..next_line.find('\n').unwrap_or(next_line.len())];
+
+        let chars_before_column = prefix_with_width(next_line, column).chars().count();
+
+        self.move_cursor_n_chars_right(right[..=line_end].chars().count() + chars_before_column);
+        true
+    }
+
     #[inline]
     pub fn insert_char(&mut self, c: char) {
         let char_utf8_len = c.len_utf8();
-
-        if char_utf8_len > self.gap_size() {
-            todo!("Resize buffer");
-        }
+        self.reserve(char_utf8_len);
 
         let mut encoded = [0; 4];
         c.encode_utf8(&mut encoded);
@@ -146,9 +247,7 @@ impl TextBox {
     }
     
     pub unsafe fn insert_str_with_cached_char_count(&mut self, s: &str, char_count: usize) {
-        if s.len() > self.gap_size() {
-            todo!("Resize buffer");
-        }
+        self.reserve(s.len());
 
         let new_gap_start = self.gap_start + s.len();
 
@@ -167,6 +266,25 @@ impl TextBox {
         self.gap_end - self.gap_start
     }
 
+    /// Grows the buffer so that the gap has room for at least `additional` bytes.
+    /// The capacity at least doubles, so inserting is amortized constant time.
+    fn reserve(&mut self, additional: usize) {
+        if additional <= self.gap_size() {
+            return;
+        }
+
+        let used = self.buffer.len() - self.gap_size();
+        let capacity = (self.buffer.len() * 2).max(used + additional);
+        let right_len = self.buffer.len() - self.gap_end;
+
+        let mut buffer = Box::new_uninit_slice(capacity);
+        buffer[..self.gap_start].copy_from_slice(&self.buffer[..self.gap_start]);
+        buffer[capacity - right_len..].copy_from_slice(&self.buffer[self.gap_end..]);
+
+        self.buffer = buffer;
+        self.gap_end = capacity - right_len;
+    }
+
     #[inline]
     pub fn parts(&self) -> (&str, &str) {
         unsafe {
@@ -175,20 +293,117 @@ impl TextBox {
         }
     }
 
-    #[inline]
-    pub fn remove_char_left(&mut self) {
-        if self.chars_left_from_cursor == 0 {
-            return;
+    /// Removes the grapheme cluster left from the cursor, e.g. a letter together with its
+    /// accents or an emoji made of several code points.
+    pub fn remove_grapheme_left(&mut self) {
+        let (left, _) = self.parts();
+
+        if let Some(grapheme) = left.graphemes(true).next_back() {
+            self.remove_n_chars_left(grapheme.chars().count());
         }
+    }
 
-        let mut char_utf8_len = 1;
+    /// Removes the grapheme cluster right from the cursor.
+    pub fn remove_grapheme_right(&mut self) {
+        let (_, right) = self.parts();
 
-        while unsafe { self.buffer[self.gap_start - char_utf8_len].assume_init() } & 0b1100_0000 == 0b1000_0000 {
-            char_utf8_len += 1;
+        if let Some(grapheme) = right.graphemes(true).next() {
+            self.remove_n_chars_right(grapheme.chars().count());
         }
+    }
 
-        self.gap_start -= char_utf8_len;
-        self.chars_left_from_cursor -= 1;
+    /// Removes the text from the start of the line to the cursor and returns it.
+    pub fn kill_to_line_start(&mut self) -> String {
+        let (left, _) = self.parts();
+        let line = &left[left.rfind('\n').map_or(0, |index| index + 1)..];
+
+        self.remove_n_chars_left(line.chars().count())
+    }
+
+    /// Removes the text from the cursor to the end of the line and returns it. At the end
+    /// of a line, the line break is removed to join the next line.
+    pub fn kill_to_line_end(&mut self) -> String {
+        let (_, right) = self.parts();
+        let line = &right[..right.find('\n').unwrap_or(right.len())];
+
+        let n = match line.chars().count() {
+            0 if !right.is_empty() => 1,
+            n => n,
+        };
+
+        self.remove_n_chars_right(n)
+    }
+
+    /// Removes the text from the previous whitespace to the cursor and returns it.
+    pub fn kill_word_left(&mut self) -> String {
+        let (left, _) = self.parts();
+        let n = chars_of_word(left.graphemes(true).rev(), |grapheme| !grapheme.trim().is_empty());
+
+        self.remove_n_chars_left(n)
+    }
+
+    /// Removes the text from the cursor to the end of the next word and returns it.
+    pub fn kill_word_right(&mut self) -> String {
+        let (_, right) = self.parts();
+        let n = chars_of_word(right.graphemes(true), is_word);
+
+        self.remove_n_chars_right(n)
+    }
+
+    /// Swaps the grapheme clusters left and right from the cursor and moves the cursor
+    /// past both. At the end of a line, the two clusters before the cursor are swapped.
+    pub fn transpose_graphemes(&mut self) {
+        let (_, right) = self.parts();
+
+        if right.is_empty() || right.starts_with('\n') {
+            self.move_cursor_left();
+        }
+
+        let (left, right) = self.parts();
+
+        let (Some(before), Some(after)) = (left.graphemes(true).next_back(), right.graphemes(true).next()) else {
+            return;
+        };
+
+        if before == "\n" || after == "\n" {
+            return;
+        }
+
+        let (before, after) = (before.chars().count(), after.chars().count());
+
+        let before = self.remove_n_chars_left(before);
+        let after = self.remove_n_chars_right(after);
+
+        self.insert_str(&after);
+        self.insert_str(&before);
+    }
+
+    /// Removes `n` chars left from the cursor and returns them.
+    pub fn remove_n_chars_left(&mut self, n: usize) -> String {
+        let n = n.min(self.chars_left_from_cursor);
+
+        let (left, _) = self.parts();
+        let start = left.char_indices().rev().nth(n.wrapping_sub(1)).map_or(left.len(), |(index, _)| index);
+        let removed = left[start..].to_string();
+
+        self.gap_start = start;
+        self.chars_left_from_cursor -= n;
+
+        removed
+    }
+
+    /// Removes `n` chars right from the cursor and returns them.
+    pub fn remove_n_chars_right(&mut self, n: usize) -> String {
+        let n = n.min(self.chars_right_from_cursor);
+
+        let (_, right) = self.parts();
+        let end = right.char_indices().nth(n).map_or(right.len(), |(index, _)| index);
+        let removed = right[..end].to_string();
+
+        self.gap_end += end;
+        self.chars_right_from_cursor -= n;
+
+        removed
     }
 
     #[inline]
@@ -223,4 +438,143 @@ impl TextBox {
         self.gap_start += bytes_to_move;
         self.gap_end += bytes_to_move;
     }
+}
+
+/// Returns `true` if the grapheme cluster is part of a word for word motions.
+#[inline]
+pub fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns the number of chars up to the end of the next word in the grapheme clusters,
+/// skipping what isn't part of a word before it.
+pub fn chars_of_word<'a>(graphemes: impl Iterator<Item = &'a str>, is_word: impl Fn(&str) -> bool) -> usize {
+    let mut graphemes = graphemes.peekable();
+    let mut n = 0;
+
+    while let Some(grapheme) = graphemes.next_if(|grapheme| !is_word(grapheme)) {
+        n += grapheme.chars().count();
+    }
+
+    while let Some(grapheme) = graphemes.next_if(|grapheme| is_word(grapheme)) {
+        n += grapheme.chars().count();
+    }
+
+    n
+}
+
+/// Returns the longest prefix of whole grapheme clusters of the line that is at most
+/// `width` columns wide.
+fn prefix_with_width(line: &str, width: usize) -> &str {
+    let mut prefix_width = 0;
+
+    for (index, grapheme) in line.grapheme_indices(true) {
+        prefix_width += grapheme.width();
+
+        if prefix_width > width {
+            return &line[..index];
+        }
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_move_between_lines() {
+        let mut text_box = TextBox::new();
+        text_box.insert_str("ls |\nwhere size > 1KiB\n| é");
+
+        assert!(!text_box.move_cursor_down());
+        assert!(text_box.move_cursor_up());
+        assert_eq!(text_box.parts(), ("ls |\nwhe", "re size > 1KiB\n| é"));
+
+        text_box.move_cursor_n_chars_right(8);
+        assert!(text_box.move_cursor_up());
+        assert_eq!(text_box.parts(), ("ls |", "\nwhere size > 1KiB\n| é"));
+        assert!(!text_box.move_cursor_up());
+
+        assert!(text_box.move_cursor_down());
+        assert_eq!(text_box.parts(), ("ls |\nwher", "e size > 1KiB\n| é"));
+        assert!(text_box.move_cursor_down());
+        assert_eq!(text_box.parts(), ("ls |\nwhere size > 1KiB\n| é", ""));
+    }
+
+    #[test]
+    pub fn test_grow() {
+        let mut text_box = TextBox::with_capacity(4);
+        text_box.insert_str("ab");
+        text_box.move_cursor_n_chars_left(1);
+
+        text_box.insert_str("日本");
+        text_box.insert_char('語');
+        assert_eq!(text_box.parts(), ("a日本語", "b"));
+
+        let long = "x".repeat(5000);
+        text_box.insert_str(&long);
+        text_box.move_cursor_n_chars_right(1);
+        assert_eq!(text_box.parts(), (format!("a日本語{}b", long).as_str(), ""));
+    }
+
+    #[test]
+    pub fn test_graphemes() {
+        let mut text_box = TextBox::new();
+        text_box.insert_str("ae\u{301}👍🏽x");
+
+        text_box.move_cursor_left();
+        text_box.move_cursor_left();
+        assert_eq!(text_box.parts(), ("ae\u{301}", "👍🏽x"));
+
+        text_box.remove_grapheme_left();
+        assert_eq!(text_box.parts(), ("a", "👍🏽x"));
+
+        text_box.move_cursor_right();
+        assert_eq!(text_box.parts(), ("a👍🏽", "x"));
+
+        // Wide characters take up two columns.
+        text_box.clear();
+        text_box.insert_str("日本語\nabcdef");
+        text_box.move_cursor_n_chars_left(2);
+
+        assert!(text_box.move_cursor_up());
+        assert_eq!(text_box.parts(), ("日本", "語\nabcdef"));
+        assert!(text_box.move_cursor_down());
+        assert_eq!(text_box.parts(), ("日本語\nabcd", "ef"));
+    }
+
+    #[test]
+    pub fn test_words_and_kills() {
+        let mut text_box = TextBox::new();
+        text_box.insert_str("ls src/read | sort-by size\nlength");
+
+        text_box.move_cursor_up();
+        text_box.move_cursor_word_left();
+        assert_eq!(text_box.parts(), ("ls ", "src/read | sort-by size\nlength"));
+        text_box.move_cursor_word_right();
+        text_box.move_cursor_word_right();
+        text_box.move_cursor_word_right();
+        assert_eq!(text_box.parts(), ("ls src/read | sort", "-by size\nlength"));
+
+        assert_eq!(text_box.kill_word_left(), "sort");
+        assert_eq!(text_box.kill_word_right(), "-by");
+        assert_eq!(text_box.kill_to_line_end(), " size");
+        assert_eq!(text_box.kill_to_line_end(), "\n");
+        assert_eq!(text_box.kill_to_line_start(), "ls src/read | ");
+        assert_eq!(text_box.parts(), ("", "length"));
+
+        text_box.move_cursor_to_line_end();
+        text_box.transpose_graphemes();
+        assert_eq!(text_box.parts(), ("lenght", ""));
+
+        text_box.move_cursor_to_line_start();
+        text_box.move_cursor_right();
+        text_box.transpose_graphemes();
+        assert_eq!(text_box.parts(), ("el", "nght"));
+
+        text_box.remove_grapheme_right();
+        assert_eq!(text_box.parts(), ("el", "ght"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+//! # Undo and redo of edits in the [TextBox].
+
+use crate::text_box::TextBox;
+
+/// The input and the cursor, as a char index, before or after an edit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    text: String,
+    cursor: usize,
+}
+
+impl Snapshot {
+    pub fn of(text_box: &TextBox) -> Self {
+        let (left, right) = text_box.parts();
+
+        Self {
+            text: format!("{}{}", left, right),
+            cursor: text_box.chars_left_from_cursor(),
+        }
+    }
+
+    fn restore(&self, text_box: &mut TextBox) {
+        text_box.clear();
+        text_box.insert_str(&self.text);
+        text_box.move_cursor_to(self.cursor);
+    }
+}
+
+/// The states of the input before the edits that can be undone, the newest last.
+pub struct UndoStack {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Whether the last edit inserted text, so that the next insert joins it.
+    inserting: bool,
+}
+
+impl UndoStack {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            inserting: false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.inserting = false;
+    }
+
+    /// Records the edit of a key, given the input from `before` it. Consecutive inserts
+    /// are undone in one step, until the cursor moves or another edit happens.
+    pub fn record(&mut self, before: Snapshot, text_box: &TextBox, insert: bool) {
+        let (left, right) = text_box.parts();
+        let text = before.text.as_bytes();
+
+        let unchanged = text.len() == left.len() + right.len()
+            && text.starts_with(left.as_bytes())
+            && text.ends_with(right.as_bytes());
+
+        if unchanged {
+            if before.cursor != text_box.chars_left_from_cursor() {
+                self.inserting = false;
+            }
+
+            return;
+        }
+
+        if !(insert && self.inserting) {
+            self.undo.push(before);
+        }
+
+        self.redo.clear();
+        self.inserting = insert;
+    }
+
+    /// Restores the input and cursor from before the last edit.
+    pub fn undo(&mut self, text_box: &mut TextBox) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(Snapshot::of(text_box));
+            snapshot.restore(text_box);
+        }
+
+        self.inserting = false;
+    }
+
+    /// Restores the input and cursor from before the last undo.
+    pub fn redo(&mut self, text_box: &mut TextBox) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(Snapshot::of(text_box));
+            snapshot.restore(text_box);
+        }
+
+        self.inserting = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text_box: &TextBox) -> (String, usize) {
+        let (left, right) = text_box.parts();
+        (format!("{}{}", left, right), text_box.chars_left_from_cursor())
+    }
+
+    #[test]
+    pub fn test_undo_stack() {
+        let mut text_box = TextBox::new();
+        let mut undo_stack = UndoStack::new();
+
+        for c in "ls src".chars() {
+            let before = Snapshot::of(&text_box);
+            text_box.insert_char(c);
+            undo_stack.record(before, &text_box, true);
+        }
+
+        let before = Snapshot::of(&text_box);
+        text_box.move_cursor_left();
+        undo_stack.record(before, &text_box, false);
+
+        let before = Snapshot::of(&text_box);
+        text_box.insert_char('x');
+        undo_stack.record(before, &text_box, true);
+
+        let before = Snapshot::of(&text_box);
+        text_box.kill_word_left();
+        undo_stack.record(before, &text_box, false);
+
+        assert_eq!(text(&text_box), ("ls c".to_string(), 3));
+
+        undo_stack.undo(&mut text_box);
+        assert_eq!(text(&text_box), ("ls srxc".to_string(), 6));
+
+        // Inserts after the cursor moved are a separate step.
+        undo_stack.undo(&mut text_box);
+        assert_eq!(text(&text_box), ("ls src".to_string(), 5));
+
+        undo_stack.undo(&mut text_box);
+        assert_eq!(text(&text_box), ("".to_string(), 0));
+
+        undo_stack.undo(&mut text_box);
+        assert_eq!(text(&text_box), ("".to_string(), 0));
+
+        undo_stack.redo(&mut text_box);
+        undo_stack.redo(&mut text_box);
+        assert_eq!(text(&text_box), ("ls srxc".to_string(), 6));
+
+        // A new edit drops what could be redone.
+        let before = Snapshot::of(&text_box);
+        text_box.remove_grapheme_left();
+        undo_stack.record(before, &text_box, false);
+
+        undo_stack.redo(&mut text_box);
+        assert_eq!(text(&text_box), ("ls src".to_string(), 5));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+//! # Units of quantities like `10MB` or `250ms`.
+
+use std::fmt::{Display, Formatter};
+
+/// What a unit measures. Quantities can only be added, compared or converted if
+/// their units have the same dimension.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Dimension {
+    Size,
+    Duration,
+    Length,
+    Mass,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    pub name: &'static str,
+    pub dimension: Dimension,
+    /// How many base units (bytes, seconds, meters or grams) one of this unit is.
+    pub factor: f64,
+}
+
+macro_rules! units {
+    ($($name: literal => $dimension: ident * $factor: expr),+ $(,)?) => {
+        &[$(Unit { name: $name, dimension: Dimension::$dimension, factor: $factor }),+]
+    };
+}
+
+const KIB: f64 = 1024.0;
+
+pub static UNITS: &[Unit] = units! {
+    "B" => Size * 1.0,
+    "kB" => Size * 1e3,
+    "MB" => Size * 1e6,
+    "GB" => Size * 1e9,
+    "TB" => Size * 1e12,
+    "PB" => Size * 1e15,
+    "KiB" => Size * KIB,
+    "MiB" => Size * (KIB * KIB),
+    "GiB" => Size * (KIB * KIB * KIB),
+    "TiB" => Size * (KIB * KIB * KIB * KIB),
+    "PiB" => Size * (KIB * KIB * KIB * KIB * KIB),
+
+    "ns" => Duration * 1e-9,
+    "us" => Duration * 1e-6,
+    "ms" => Duration * 1e-3,
+    "s" => Duration * 1.0,
+    "min" => Duration * 60.0,
+    "h" => Duration * 3600.0,
+    "d" => Duration * 86400.0,
+
+    "nm" => Length * 1e-9,
+    "um" => Length * 1e-6,
+    "mm" => Length * 1e-3,
+    "cm" => Length * 1e-2,
+    "m" => Length * 1.0,
+    "km" => Length * 1e3,
+
+    "mg" => Mass * 1e-3,
+    "g" => Mass * 1.0,
+    "kg" => Mass * 1e3,
+    "t" => Mass * 1e6,
+};
+
+/// Returns the unit with the given name. Names are case-sensitive, `MB` and `mB` differ.
+pub fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.name == name)
+}
+
+/// Returns the unit with the given name, which has to exist.
+fn unit(name: &str) -> &'static Unit {
+    find_unit(name).expect("unit is defined")
+}
+
+/// An amount of a unit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quantity {
+    pub amount: f64,
+    pub unit: &'static Unit,
+}
+
+impl Quantity {
+    #[inline]
+    pub const fn new(amount: f64, unit: &'static Unit) -> Self {
+        Self { amount, unit }
+    }
+
+    /// Returns a size of `bytes` in the largest binary unit, like `KiB` or `MiB`,
+    /// that keeps the amount at least one.
+    pub fn size(bytes: u64) -> Self {
+        let unit = ["PiB", "TiB", "GiB", "MiB", "KiB"]
+            .into_iter()
+            .map(unit)
+            .find(|unit| bytes as f64 >= unit.factor)
+            .unwrap_or(unit("B"));
+
+        Self::new(bytes as f64 / unit.factor, unit)
+    }
+
+    /// Returns a duration in the largest unit up to hours that keeps the amount at least
+    /// one, like `1.5min`.
+    pub fn duration(seconds: f64) -> Self {
+        let unit = ["h", "min", "s", "ms", "us"]
+            .into_iter()
+            .map(unit)
+            .find(|unit| seconds >= unit.factor)
+            .unwrap_or(unit("ns"));
+
+        Self::new(seconds / unit.factor, unit)
+    }
+
+    /// Returns the amount in the base unit of the dimension.
+    #[inline]
+    pub fn base_amount(&self) -> f64 {
+        self.amount * self.unit.factor
+    }
+
+    /// Converts the quantity into another unit of the same dimension.
+    pub fn to(&self, unit: &'static Unit) -> Option<Self> {
+        (unit.dimension == self.unit.dimension)
+            .then(|| Self::new(self.base_amount() / unit.factor, unit))
+    }
+}
+
+impl Display for Quantity {
+    /// Writes the amount rounded to two decimals, or to two significant digits if it is
+    /// less than one, followed by the unit.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        /// Smaller amounts are written as zero.
+        const MAX_DECIMALS: i32 = 12;
+
+        let magnitude = self.amount.abs().log10().floor() as i32;
+        let decimals = 1i32.saturating_sub(magnitude).clamp(2, MAX_DECIMALS) as usize;
+
+        let amount = format!("{:.*}", decimals, self.amount);
+        let amount = amount.trim_end_matches('0').trim_end_matches('.');
+
+        write!(f, "{}{}", amount, self.unit.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_quantity_conversion() {
+        let mebibytes = Quantity::new(10.0, unit("MiB"));
+
+        assert_eq!(mebibytes.to(unit("kB")), Some(Quantity::new(10485.76, unit("kB"))));
+        assert_eq!(mebibytes.to(unit("s")), None);
+        assert_eq!(Quantity::size(1536), Quantity::new(1.5, unit("KiB")));
+        assert_eq!(Quantity::duration(90.0), Quantity::new(1.5, unit("min")));
+        assert_eq!(Quantity::duration(0.25), Quantity::new(250.0, unit("ms")));
+        assert_eq!(Quantity::size(12), Quantity::new(12.0, unit("B")));
+        assert_eq!(Quantity::new(2.0 / 3.0, unit("h")).to_string(), "0.67h");
+        assert_eq!(Quantity::new(0.25, unit("s")).to(unit("h")).unwrap().to_string(), "0.000069h");
+        assert_eq!(Quantity::new(-0.004, unit("m")).to_string(), "-0.004m");
+        assert_eq!(Quantity::new(1234.5678, unit("B")).to_string(), "1234.57B");
+        assert_eq!(Quantity::new(0.0, unit("B")).to_string(), "0B");
+    }
+}
\ No newline at end of file
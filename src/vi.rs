@@ -0,0 +1,774 @@
+//! # Vi editing mode on top of the [TextBox].
+//!
+//! Keys in normal and visual mode are collected until they form a command, like `2dw`
+//! or `ci"`. Positions are char indices into the input, and in normal mode the cursor
+//! is on the char right from it.
+
+use crate::keymap::{self, Keymap};
+use crate::text_box::TextBox;
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    Insert,
+    Normal,
+    Visual,
+}
+
+impl Mode {
+    /// Returns the indicator in front of the prompt.
+    pub fn indicator(self) -> &'static str {
+        match self {
+            Mode::Insert => "[I] ",
+            Mode::Normal => "[N] ",
+            Mode::Visual => "[V] ",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    /// `w`, the start of the next word.
+    WordForward,
+    /// `b`, the start of the previous word.
+    WordBackward,
+    /// `e`, the end of the next word.
+    WordEnd,
+    LineStart,
+    LineEnd,
+    /// `f`, `t`, `F` and `T`. `before` stops next to the char instead of on it.
+    Find { c: char, before: bool, backward: bool },
+}
+
+impl Motion {
+    /// Returns `true` if an operator includes the char the motion ends on.
+    fn is_inclusive(self) -> bool {
+        matches!(self, Motion::WordEnd | Motion::LineEnd | Motion::Find { backward: false, .. })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Object {
+    /// `iw`, the word or the whitespace under the cursor.
+    InnerWord,
+    /// `i"` or `i'`, the text between the quotes around the cursor.
+    InnerQuotes(char),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Target {
+    Motion(Motion),
+    Object(Object),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Command {
+    Operate(Operator, Target),
+    /// `dd`, `cc` and `yy`.
+    OperateLines(Operator),
+    /// `i`, `a`, `I` and `A`.
+    Insert(Motion),
+    /// `p` and `P`.
+    Put { after: bool },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Action {
+    Move(Motion),
+    Command(Command),
+    /// `.`, repeats the last change.
+    Repeat,
+    /// `v` in normal mode and the operators in visual mode.
+    Visual(Option<Operator>),
+    SelectObject(Object),
+    /// Keys that do what the line editor does without vi mode, like `j` and `k` move
+    /// through the lines and the history.
+    Editor(keymap::Action),
+}
+
+#[derive(Debug, PartialEq)]
+enum Parsed<T> {
+    Incomplete,
+    Invalid,
+    Complete(T),
+}
+
+/// A command that changed the input, with the text that was typed after it.
+#[derive(Clone)]
+struct Change {
+    count: usize,
+    command: Command,
+    inserted: String,
+}
+
+/// Yanked or deleted text. Whole lines are put as new lines.
+#[derive(Default)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+pub struct Vi {
+    pub mode: Mode,
+    pending: Vec<char>,
+    /// The other end of the selection in visual mode.
+    anchor: usize,
+    register: Register,
+    last_change: Option<Change>,
+    /// The change that entered insert mode, and where the insertion started.
+    recording: Option<(Change, usize)>,
+}
+
+impl Vi {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Insert,
+            pending: Vec::new(),
+            anchor: 0,
+            register: Register::default(),
+            last_change: None,
+            recording: None,
+        }
+    }
+
+    /// Starts a new input in insert mode.
+    pub fn reset(&mut self) {
+        self.mode = Mode::Insert;
+        self.pending.clear();
+        self.recording = None;
+    }
+
+    /// Returns the bytes of the input that are selected in visual mode.
+    pub fn selection(&self, text_box: &TextBox) -> Option<Range<usize>> {
+        if self.mode != Mode::Visual {
+            return None;
+        }
+
+        let (left, right) = text_box.parts();
+        let text = format!("{}{}", left, right);
+        let cursor = text_box.chars_left_from_cursor();
+
+        let byte = |index| text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte);
+
+        Some(byte(self.anchor.min(cursor))..byte(self.anchor.max(cursor) + 1))
+    }
+
+    /// Handles a key and returns the action for the line editor, if any. In insert mode,
+    /// all keys except Escape have their action from the `keymap`.
+    pub fn handle_key(
+        &mut self,
+        text_box: &mut TextBox,
+        keymap: &Keymap,
+        key: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> Option<keymap::Action> {
+        let control = modifiers.contains(KeyModifiers::CONTROL);
+
+        match (self.mode, key) {
+            (Mode::Insert, KeyCode::Esc) => {
+                self.leave_insert(text_box);
+                None
+            }
+            // Terminals send Escape and a quickly following key as that key with Alt.
+            (Mode::Insert, KeyCode::Char(c)) if modifiers.contains(KeyModifiers::ALT) && !control => {
+                self.leave_insert(text_box);
+                self.handle_char(text_box, c)
+            }
+            (Mode::Insert, key) => keymap.action(key, modifiers),
+            (_, KeyCode::Esc) => {
+                self.pending.clear();
+                self.mode = Mode::Normal;
+                None
+            }
+            (_, KeyCode::Char(c)) if !control => self.handle_char(text_box, c),
+            (_, KeyCode::Backspace) => Some(keymap::Action::MoveLeft),
+            (_, KeyCode::Char('r')) if control => Some(keymap::Action::Redo),
+            (_, key) => keymap.action(key, modifiers),
+        }
+    }
+
+    fn handle_char(&mut self, text_box: &mut TextBox, c: char) -> Option<keymap::Action> {
+        self.pending.push(c);
+
+        let parsed = match self.mode {
+            Mode::Visual => parse_visual(&self.pending),
+            _ => parse_normal(&self.pending),
+        };
+
+        let (count, action) = match parsed {
+            Parsed::Incomplete => return None,
+            Parsed::Invalid => {
+                self.pending.clear();
+                return None;
+            }
+            Parsed::Complete(complete) => complete,
+        };
+
+        self.pending.clear();
+
+        let key = self.execute(text_box, count, action);
+
+        if self.mode == Mode::Normal {
+            clamp_cursor(text_box);
+        }
+
+        key
+    }
+
+    fn execute(&mut self, text_box: &mut TextBox, count: usize, action: Action) -> Option<keymap::Action> {
+        let text = text(text_box);
+        let cursor = text_box.chars_left_from_cursor();
+
+        match action {
+            Action::Move(motion) => {
+                if let Some(target) = repeat_motion(&text, cursor, motion, count) {
+                    text_box.move_cursor_to(target);
+                }
+            }
+            Action::Command(command) => {
+                self.run(text_box, count, command);
+
+                let change = Change { count, command, inserted: String::new() };
+
+                if self.mode == Mode::Insert {
+                    self.recording = Some((change, text_box.chars_left_from_cursor()));
+                } else if !matches!(command, Command::Operate(Operator::Yank, _) | Command::OperateLines(Operator::Yank)) {
+                    self.last_change = Some(change);
+                }
+            }
+            Action::Repeat => {
+                if let Some(change) = self.last_change.clone() {
+                    self.run(text_box, change.count, change.command);
+
+                    if self.mode == Mode::Insert {
+                        text_box.insert_str(&change.inserted);
+                        self.leave_insert(text_box);
+                        self.last_change = Some(change);
+                    }
+                }
+            }
+            Action::Visual(None) => {
+                self.mode = if self.mode == Mode::Visual { Mode::Normal } else { Mode::Visual };
+                self.anchor = cursor;
+            }
+            Action::Visual(Some(operator)) => {
+                let range = self.anchor.min(cursor)..(self.anchor.max(cursor) + 1).min(text.len());
+
+                self.mode = Mode::Normal;
+                self.operate(text_box, &text, operator, range);
+            }
+            Action::SelectObject(object) => {
+                if let Some(range) = object_range(&text, cursor, object).filter(|range| !range.is_empty()) {
+                    self.anchor = range.start;
+                    text_box.move_cursor_to(range.end - 1);
+                }
+            }
+            Action::Editor(action) => return Some(action),
+        }
+
+        None
+    }
+
+    fn run(&mut self, text_box: &mut TextBox, count: usize, command: Command) {
+        let text = text(text_box);
+        let cursor = text_box.chars_left_from_cursor();
+
+        match command {
+            Command::Operate(operator, Target::Motion(mut motion)) => {
+                // `cw` changes to the end of the word, like `ce`.
+                if operator == Operator::Change && motion == Motion::WordForward
+                    && text.get(cursor).is_some_and(|c| !c.is_whitespace())
+                {
+                    motion = Motion::WordEnd;
+                }
+
+                let Some(target) = repeat_motion(&text, cursor, motion, count) else {
+                    return;
+                };
+
+                let end = (cursor.max(target) + motion.is_inclusive() as usize).min(text.len());
+
+                self.operate(text_box, &text, operator, cursor.min(target)..end);
+            }
+            Command::Operate(operator, Target::Object(object)) => {
+                if let Some(range) = object_range(&text, cursor, object) {
+                    self.operate(text_box, &text, operator, range);
+                }
+            }
+            Command::OperateLines(operator) => {
+                let start = line_start(&text, cursor);
+                let mut end = line_end(&text, cursor);
+
+                for _ in 1..count {
+                    if end < text.len() {
+                        end = line_end(&text, end + 1);
+                    }
+                }
+
+                self.register = Register {
+                    text: text[start..end].iter().collect(),
+                    linewise: true,
+                };
+
+                match operator {
+                    Operator::Yank => {}
+                    Operator::Change => {
+                        remove(text_box, start..end);
+                        self.mode = Mode::Insert;
+                    }
+                    Operator::Delete => {
+                        // Remove one of the line breaks around the lines too.
+                        let range = if end < text.len() {
+                            start..end + 1
+                        } else {
+                            start.saturating_sub(1)..end
+                        };
+
+                        remove(text_box, range);
+                    }
+                }
+            }
+            Command::Insert(motion) => {
+                let target = match motion {
+                    Motion::Right => (cursor + 1).min(line_end(&text, cursor)),
+                    Motion::LineStart => first_non_blank(&text, cursor),
+                    Motion::LineEnd => line_end(&text, cursor),
+                    motion => apply_motion(&text, cursor, motion).unwrap_or(cursor),
+                };
+
+                text_box.move_cursor_to(target);
+                self.mode = Mode::Insert;
+            }
+            Command::Put { after } => {
+                let put = self.register.text.repeat(count);
+
+                if put.is_empty() {
+                    return;
+                }
+
+                if self.register.linewise {
+                    if after {
+                        text_box.move_cursor_to(line_end(&text, cursor));
+                        text_box.insert_char('\n');
+                        text_box.insert_str(&put);
+                        text_box.move_cursor_to(line_end(&text, cursor) + 1);
+                    } else {
+                        text_box.move_cursor_to(line_start(&text, cursor));
+                        text_box.insert_str(&put);
+                        text_box.insert_char('\n');
+                        text_box.move_cursor_to(line_start(&text, cursor));
+                    }
+                } else {
+                    if after && cursor < line_end(&text, cursor) {
+                        text_box.move_cursor_right();
+                    }
+
+                    text_box.insert_str(&put);
+                    text_box.move_cursor_left();
+                }
+            }
+        }
+    }
+
+    fn operate(&mut self, text_box: &mut TextBox, text: &[char], operator: Operator, range: Range<usize>) {
+        self.register = Register {
+            text: text[range.clone()].iter().collect(),
+            linewise: false,
+        };
+
+        match operator {
+            Operator::Yank => text_box.move_cursor_to(range.start),
+            Operator::Delete => remove(text_box, range),
+            Operator::Change => {
+                remove(text_box, range);
+                self.mode = Mode::Insert;
+            }
+        }
+    }
+
+    /// Returns to normal mode, keeps what was typed for `.` and moves the cursor onto
+    /// the last inserted char.
+    fn leave_insert(&mut self, text_box: &mut TextBox) {
+        self.mode = Mode::Normal;
+
+        if let Some((mut change, start)) = self.recording.take() {
+            let cursor = text_box.chars_left_from_cursor();
+
+            change.inserted = text(text_box)
+                .get(start..cursor)
+                .map_or(String::new(), |inserted| inserted.iter().collect());
+
+            self.last_change = Some(change);
+        }
+
+        let text = text(text_box);
+        let cursor = text_box.chars_left_from_cursor();
+
+        if cursor > line_start(&text, cursor) {
+            text_box.move_cursor_left();
+        }
+    }
+}
+
+/// Parses the keys of a command in normal mode, like `3w`, `d2w`, `dd` or `ci"`.
+fn parse_normal(keys: &[char]) -> Parsed<(usize, Action)> {
+    let (count, keys) = split_count(keys);
+
+    let action = match keys {
+        [] => return Parsed::Incomplete,
+        ['i'] => Action::Command(Command::Insert(Motion::Left)),
+        ['a'] => Action::Command(Command::Insert(Motion::Right)),
+        ['I'] => Action::Command(Command::Insert(Motion::LineStart)),
+        ['A'] => Action::Command(Command::Insert(Motion::LineEnd)),
+        ['x'] => Action::Command(Command::Operate(Operator::Delete, Target::Motion(Motion::Right))),
+        ['X'] => Action::Command(Command::Operate(Operator::Delete, Target::Motion(Motion::Left))),
+        ['s'] => Action::Command(Command::Operate(Operator::Change, Target::Motion(Motion::Right))),
+        ['D'] => Action::Command(Command::Operate(Operator::Delete, Target::Motion(Motion::LineEnd))),
+        ['C'] => Action::Command(Command::Operate(Operator::Change, Target::Motion(Motion::LineEnd))),
+        ['p'] => Action::Command(Command::Put { after: true }),
+        ['P'] => Action::Command(Command::Put { after: false }),
+        ['v'] => Action::Visual(None),
+        ['.'] => Action::Repeat,
+        ['j'] => Action::Editor(keymap::Action::MoveDown),
+        ['k'] => Action::Editor(keymap::Action::MoveUp),
+        ['u'] => Action::Editor(keymap::Action::Undo),
+        [operator @ ('d' | 'c' | 'y'), keys @ ..] => {
+            let operator_key = *operator;
+            let operator = match operator_key {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+
+            let (motion_count, keys) = split_count(keys);
+
+            let command = match keys {
+                [] | ['i'] => return Parsed::Incomplete,
+                [key] if *key == operator_key => Command::OperateLines(operator),
+                ['i', key] => match parse_object(*key) {
+                    Some(object) => Command::Operate(operator, Target::Object(object)),
+                    None => return Parsed::Invalid,
+                },
+                keys => match parse_motion(keys) {
+                    Parsed::Complete(motion) => Command::Operate(operator, Target::Motion(motion)),
+                    Parsed::Incomplete => return Parsed::Incomplete,
+                    Parsed::Invalid => return Parsed::Invalid,
+                },
+            };
+
+            return Parsed::Complete((count * motion_count, Action::Command(command)));
+        }
+        keys => match parse_motion(keys) {
+            Parsed::Complete(motion) => Action::Move(motion),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            Parsed::Invalid => return Parsed::Invalid,
+        },
+    };
+
+    Parsed::Complete((count, action))
+}
+
+/// Parses the keys of a command in visual mode, which are motions and operators.
+fn parse_visual(keys: &[char]) -> Parsed<(usize, Action)> {
+    let (count, keys) = split_count(keys);
+
+    let action = match keys {
+        [] | ['i'] => return Parsed::Incomplete,
+        ['v'] => Action::Visual(None),
+        ['d' | 'x'] => Action::Visual(Some(Operator::Delete)),
+        ['c' | 's'] => Action::Visual(Some(Operator::Change)),
+        ['y'] => Action::Visual(Some(Operator::Yank)),
+        ['i', key] => match parse_object(*key) {
+            Some(object) => Action::SelectObject(object),
+            None => return Parsed::Invalid,
+        },
+        keys => match parse_motion(keys) {
+            Parsed::Complete(motion) => Action::Move(motion),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            Parsed::Invalid => return Parsed::Invalid,
+        },
+    };
+
+    Parsed::Complete((count, action))
+}
+
+fn parse_motion(keys: &[char]) -> Parsed<Motion> {
+    Parsed::Complete(match keys {
+        [] | ['f' | 't' | 'F' | 'T'] => return Parsed::Incomplete,
+        ['h'] => Motion::Left,
+        ['l' | ' '] => Motion::Right,
+        ['w'] => Motion::WordForward,
+        ['b'] => Motion::WordBackward,
+        ['e'] => Motion::WordEnd,
+        ['0' | '^'] => Motion::LineStart,
+        ['$'] => Motion::LineEnd,
+        [find @ ('f' | 't' | 'F' | 'T'), c] => Motion::Find {
+            c: *c,
+            before: matches!(find, 't' | 'T'),
+            backward: matches!(find, 'F' | 'T'),
+        },
+        _ => return Parsed::Invalid,
+    })
+}
+
+fn parse_object(key: char) -> Option<Object> {
+    match key {
+        'w' => Some(Object::InnerWord),
+        '"' | '\'' | '`' => Some(Object::InnerQuotes(key)),
+        _ => None,
+    }
+}
+
+/// Splits a count off the keys. A `0` alone is a motion, not a count.
+fn split_count(keys: &[char]) -> (usize, &[char]) {
+    let digits = match keys.first() {
+        Some('1'..='9') => keys.iter().take_while(|key| key.is_ascii_digit()).count(),
+        _ => 0,
+    };
+
+    let count = keys[..digits]
+        .iter()
+        .fold(0usize, |count, digit| count.saturating_mul(10).saturating_add(*digit as usize - '0' as usize));
+
+    (count.max(1), &keys[digits..])
+}
+
+#[inline]
+fn text(text_box: &TextBox) -> Vec<char> {
+    let (left, right) = text_box.parts();
+    left.chars().chain(right.chars()).collect()
+}
+
+#[inline]
+fn remove(text_box: &mut TextBox, range: Range<usize>) {
+    text_box.move_cursor_to(range.end);
+    text_box.remove_n_chars_left(range.len());
+}
+
+/// Keeps the cursor on a char in normal mode, which can't be after the end of a line.
+fn clamp_cursor(text_box: &mut TextBox) {
+    let text = text(text_box);
+    let cursor = text_box.chars_left_from_cursor();
+
+    if cursor == line_end(&text, cursor) && cursor > line_start(&text, cursor) {
+        text_box.move_cursor_left();
+    }
+}
+
+/// The classes of chars for word motions: whitespace, word chars and punctuation.
+#[inline]
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn line_start(text: &[char], index: usize) -> usize {
+    text[..index].iter().rposition(|c| *c == '\n').map_or(0, |newline| newline + 1)
+}
+
+fn line_end(text: &[char], index: usize) -> usize {
+    text[index..].iter().position(|c| *c == '\n').map_or(text.len(), |newline| index + newline)
+}
+
+fn first_non_blank(text: &[char], index: usize) -> usize {
+    let start = line_start(text, index);
+    let end = line_end(text, index);
+
+    text[start..end].iter().position(|c| !c.is_whitespace()).map_or(end, |offset| start + offset)
+}
+
+fn repeat_motion(text: &[char], index: usize, motion: Motion, count: usize) -> Option<usize> {
+    (0..count).try_fold(index, |index, _| apply_motion(text, index, motion))
+}
+
+/// Returns where the motion moves the cursor at `index`, or `None` if it can't move.
+fn apply_motion(text: &[char], index: usize, motion: Motion) -> Option<usize> {
+    let len = text.len();
+
+    Some(match motion {
+        Motion::Left => index.saturating_sub(1).max(line_start(text, index)),
+        Motion::Right => (index + 1).min(line_end(text, index)),
+        Motion::WordForward => {
+            let mut index = index;
+
+            if let Some(c) = text.get(index).map(|c| class(*c)).filter(|class| *class != 0) {
+                while index < len && class(text[index]) == c {
+                    index += 1;
+                }
+            }
+
+            while index < len && class(text[index]) == 0 {
+                index += 1;
+            }
+
+            index
+        }
+        Motion::WordBackward => {
+            let mut index = index;
+
+            while index > 0 && class(text[index - 1]) == 0 {
+                index -= 1;
+            }
+
+            if let Some(c) = index.checked_sub(1).map(|previous| class(text[previous])) {
+                while index > 0 && class(text[index - 1]) == c {
+                    index -= 1;
+                }
+            }
+
+            index
+        }
+        Motion::WordEnd => {
+            let mut index = index + 1;
+
+            while index < len && class(text[index]) == 0 {
+                index += 1;
+            }
+
+            if index >= len {
+                return Some(len.saturating_sub(1));
+            }
+
+            let c = class(text[index]);
+
+            while index + 1 < len && class(text[index + 1]) == c {
+                index += 1;
+            }
+
+            index
+        }
+        Motion::LineStart => line_start(text, index),
+        Motion::LineEnd => line_end(text, index).saturating_sub(1).max(line_start(text, index)),
+        Motion::Find { c, before, backward: false } => {
+            let end = line_end(text, index);
+            let found = index + 1 + text.get(index + 1..end)?.iter().position(|other| *other == c)?;
+
+            if before { found - 1 } else { found }
+        }
+        Motion::Find { c, before, backward: true } => {
+            let start = line_start(text, index);
+            let found = start + text[start..index].iter().rposition(|other| *other == c)?;
+
+            if before { found + 1 } else { found }
+        }
+    })
+}
+
+/// Returns the chars that a text object around `index` covers.
+fn object_range(text: &[char], index: usize, object: Object) -> Option<Range<usize>> {
+    match object {
+        Object::InnerWord => {
+            let c = class(*text.get(index)?);
+            let same = |other: &char| *other != '\n' && class(*other) == c;
+
+            let start = text[..index].iter().rposition(|other| !same(other)).map_or(0, |other| other + 1);
+            let end = text[index..].iter().position(|other| !same(other)).map_or(text.len(), |other| index + other);
+
+            Some(start..end)
+        }
+        Object::InnerQuotes(quote) => {
+            let start = line_start(text, index);
+            let end = line_end(text, index);
+            let line = &text[start..end];
+            let index = index - start;
+
+            if line.is_empty() {
+                return None;
+            }
+
+            // The opening quote is at or before the cursor, or the first one after it.
+            let open = line[..=index.min(line.len() - 1)]
+                .iter()
+                .rposition(|c| *c == quote)
+                .filter(|open| line[open + 1..].contains(&quote))
+                .or_else(|| line[index..].iter().position(|c| *c == quote).map(|open| index + open))?;
+
+            let close = open + 1 + line[open + 1..].iter().position(|c| *c == quote)?;
+
+            Some(start + open + 1..start + close)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types the keys starting in normal mode, `<` is Escape, and returns the input and
+    /// the cursor.
+    fn run(input: &str, keys: &str) -> (String, usize) {
+        let mut text_box = TextBox::new();
+        text_box.insert_str(input);
+        text_box.move_cursor_to(0);
+
+        let keymap = Keymap::default();
+        let mut vi = Vi::new();
+        vi.mode = Mode::Normal;
+
+        for key in keys.chars() {
+            let key = if key == '<' { KeyCode::Esc } else { KeyCode::Char(key) };
+
+            // Keys in insert mode are typed like without vi mode.
+            if let Some(keymap::Action::Insert(c)) = vi.handle_key(&mut text_box, &keymap, key, KeyModifiers::NONE) {
+                text_box.insert_char(c);
+            }
+        }
+
+        let (left, right) = text_box.parts();
+        (format!("{}{}", left, right), text_box.chars_left_from_cursor())
+    }
+
+    #[test]
+    pub fn test_motions() {
+        assert_eq!(run("ls src/read | length", "w"), ("ls src/read | length".to_string(), 3));
+        assert_eq!(run("ls src/read | length", "2w"), ("ls src/read | length".to_string(), 6));
+        assert_eq!(run("ls src/read | length", "$b"), ("ls src/read | length".to_string(), 14));
+        assert_eq!(run("ls src/read | length", "e"), ("ls src/read | length".to_string(), 1));
+        assert_eq!(run("ls src/read | length", "f|"), ("ls src/read | length".to_string(), 12));
+        assert_eq!(run("ls src/read | length", "$T/"), ("ls src/read | length".to_string(), 7));
+        assert_eq!(run("ls src/read | length", "$0"), ("ls src/read | length".to_string(), 0));
+    }
+
+    #[test]
+    pub fn test_operators() {
+        assert_eq!(run("ls src/read | length", "dw"), ("src/read | length".to_string(), 0));
+        assert_eq!(run("ls src/read | length", "d3w"), ("read | length".to_string(), 0));
+        assert_eq!(run("ls src/read | length", "wcwlib<"), ("ls lib/read | length".to_string(), 5));
+        assert_eq!(run("ls src/read | length", "f|D"), ("ls src/read ".to_string(), 11));
+        assert_eq!(run("ls src/read | length", "3x.."), ("ad | length".to_string(), 0));
+        assert_eq!(run("ls src/read | length", "ywP"), ("ls ls src/read | length".to_string(), 2));
+        assert_eq!(run("ls src/read | length", "dd"), ("".to_string(), 0));
+        assert_eq!(run("echo \"a b\" c", "fbci\"x y<"), ("echo \"x y\" c".to_string(), 8));
+        assert_eq!(run("echo \"a b\" c", "wwdiw"), ("echo \" b\" c".to_string(), 6));
+        assert_eq!(run("", "di\""), ("".to_string(), 0));
+
+        // `j` is handled by the editor, so the cursor is put on the blank line directly.
+        let text = "a\n\nb".chars().collect::<Vec<_>>();
+        assert_eq!(object_range(&text, 2, Object::InnerQuotes('\'')), None);
+        assert_eq!(object_range(&text, 4, Object::InnerQuotes('"')), None);
+        assert_eq!(run("ls src", "Ash<0."), ("ls srcshsh".to_string(), 9));
+    }
+
+    #[test]
+    pub fn test_visual() {
+        assert_eq!(run("ls src/read | length", "wvey"), ("ls src/read | length".to_string(), 3));
+        assert_eq!(run("ls src/read | length", "wv2ed"), ("ls read | length".to_string(), 3));
+        assert_eq!(run("ls src/read | length", "wviwcpath<"), ("ls path/read | length".to_string(), 6));
+    }
+}
\ No newline at end of file